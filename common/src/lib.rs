@@ -8,7 +8,7 @@ pub mod big_endian;
 pub mod consumable_buffer;
 pub mod leb128;
 pub mod mutex;
-pub mod net;
 pub mod numbers;
+pub mod signal;
 pub mod syscalls;
 pub mod util;
@@ -0,0 +1,160 @@
+use core::fmt::{Debug, Display};
+
+/// A signal number. The numbers match the ones used by Linux.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Signal(pub(crate) usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+}
+
+impl Signal {
+    pub const SIGHUP: Signal = Signal(1);
+    pub const SIGINT: Signal = Signal(2);
+    pub const SIGQUIT: Signal = Signal(3);
+    pub const SIGILL: Signal = Signal(4);
+    pub const SIGTRAP: Signal = Signal(5);
+    pub const SIGABRT: Signal = Signal(6);
+    pub const SIGBUS: Signal = Signal(7);
+    pub const SIGFPE: Signal = Signal(8);
+    pub const SIGKILL: Signal = Signal(9);
+    pub const SIGUSR1: Signal = Signal(10);
+    pub const SIGSEGV: Signal = Signal(11);
+    pub const SIGUSR2: Signal = Signal(12);
+    pub const SIGPIPE: Signal = Signal(13);
+    pub const SIGALRM: Signal = Signal(14);
+    pub const SIGTERM: Signal = Signal(15);
+    pub const SIGCHLD: Signal = Signal(17);
+    pub const SIGCONT: Signal = Signal(18);
+    pub const SIGURG: Signal = Signal(23);
+    pub const SIGXCPU: Signal = Signal(24);
+    pub const SIGWINCH: Signal = Signal(28);
+
+    /// Signal numbers range from 1 to 63
+    pub const COUNT: usize = 64;
+
+    pub const fn new(number: usize) -> Option<Self> {
+        if number > 0 && number < Self::COUNT {
+            Some(Self(number))
+        } else {
+            None
+        }
+    }
+
+    pub const fn number(&self) -> usize {
+        self.0
+    }
+
+    pub fn default_action(&self) -> DefaultAction {
+        match *self {
+            Self::SIGCHLD | Self::SIGCONT | Self::SIGURG | Self::SIGWINCH => DefaultAction::Ignore,
+            _ => DefaultAction::Terminate,
+        }
+    }
+
+    /// SIGKILL can neither be caught, ignored nor blocked
+    pub fn can_be_caught(&self) -> bool {
+        *self != Self::SIGKILL
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match *self {
+            Self::SIGHUP => "SIGHUP",
+            Self::SIGINT => "SIGINT",
+            Self::SIGQUIT => "SIGQUIT",
+            Self::SIGILL => "SIGILL",
+            Self::SIGTRAP => "SIGTRAP",
+            Self::SIGABRT => "SIGABRT",
+            Self::SIGBUS => "SIGBUS",
+            Self::SIGFPE => "SIGFPE",
+            Self::SIGKILL => "SIGKILL",
+            Self::SIGUSR1 => "SIGUSR1",
+            Self::SIGSEGV => "SIGSEGV",
+            Self::SIGUSR2 => "SIGUSR2",
+            Self::SIGPIPE => "SIGPIPE",
+            Self::SIGALRM => "SIGALRM",
+            Self::SIGTERM => "SIGTERM",
+            Self::SIGCHLD => "SIGCHLD",
+            Self::SIGCONT => "SIGCONT",
+            Self::SIGURG => "SIGURG",
+            Self::SIGXCPU => "SIGXCPU",
+            Self::SIGWINCH => "SIGWINCH",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl Debug for Signal {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "signal {}", self.0),
+        }
+    }
+}
+
+/// Bitmask of signals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SignalSet(u64);
+
+impl SignalSet {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn insert(&mut self, signal: Signal) {
+        self.0 |= 1 << signal.0;
+    }
+
+    pub fn remove(&mut self, signal: Signal) {
+        self.0 &= !(1 << signal.0);
+    }
+
+    pub fn contains(&self, signal: Signal) -> bool {
+        self.0 & (1 << signal.0) != 0
+    }
+
+    pub fn union(&self, other: SignalSet) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn difference(&self, other: SignalSet) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    pub fn all() -> Self {
+        Self(u64::MAX)
+    }
+
+    /// Returns the signal with the lowest number
+    pub fn first(&self) -> Option<Signal> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(Signal(self.0.trailing_zeros() as usize))
+        }
+    }
+}
+
+/// Passed as handler to `sys_sigaction` to restore the default action
+pub const SIG_DFL: usize = 0;
+/// Passed as handler to `sys_sigaction` to ignore the signal
+pub const SIG_IGN: usize = 1;
@@ -67,3 +67,75 @@ pub fn ecall_3(nr: usize, arg1: usize, arg2: usize, arg3: usize) -> (usize, usiz
     }
     (ret1, ret2)
 }
+
+pub fn ecall_4(nr: usize, arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> (usize, usize) {
+    let ret1: usize;
+    let ret2: usize;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") nr,
+            in("a1") arg1,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a4") arg4,
+            lateout("a0") ret1,
+            lateout("a1") ret2,
+        );
+    }
+    (ret1, ret2)
+}
+
+pub fn ecall_5(
+    nr: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> (usize, usize) {
+    let ret1: usize;
+    let ret2: usize;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") nr,
+            in("a1") arg1,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a4") arg4,
+            in("a5") arg5,
+            lateout("a0") ret1,
+            lateout("a1") ret2,
+        );
+    }
+    (ret1, ret2)
+}
+
+pub fn ecall_6(
+    nr: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> (usize, usize) {
+    let ret1: usize;
+    let ret2: usize;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") nr,
+            in("a1") arg1,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a4") arg4,
+            in("a5") arg5,
+            in("a6") arg6,
+            lateout("a0") ret1,
+            lateout("a1") ret2,
+        );
+    }
+    (ret1, ret2)
+}
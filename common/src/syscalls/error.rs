@@ -0,0 +1,54 @@
+macro_rules! errors {
+    ($($name:ident = $code:literal => $description:literal,)*) => {
+        /// The error of every syscall. The codes match the errno values of Linux.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(usize)]
+        pub enum SysError {
+            $($name = $code,)*
+        }
+
+        impl SysError {
+            pub fn from_code(code: usize) -> Option<Self> {
+                match code {
+                    $($code => Some(Self::$name),)*
+                    _ => None,
+                }
+            }
+
+            pub fn code(self) -> usize {
+                self as usize
+            }
+
+            /// Human readable description of the error like `strerror` in C.
+            pub fn description(self) -> &'static str {
+                match self {
+                    $(Self::$name => $description,)*
+                }
+            }
+        }
+    };
+}
+
+errors!(
+    PermissionDenied = 1 => "Operation not permitted",
+    NotFound = 2 => "No such file or directory",
+    NoSuchProcess = 3 => "No such process",
+    BadDescriptor = 9 => "Bad file descriptor",
+    NoChildren = 10 => "No child processes",
+    WouldBlock = 11 => "Resource temporarily unavailable",
+    OutOfMemory = 12 => "Cannot allocate memory",
+    BadAddress = 14 => "Bad address",
+    InvalidArgument = 22 => "Invalid argument",
+    TooManyFiles = 24 => "Too many open files",
+    BrokenPipe = 32 => "Broken pipe",
+    NotImplemented = 38 => "Function not implemented",
+    MessageTooLong = 90 => "Message too long",
+    AddressInUse = 98 => "Address already in use",
+    NotConnected = 107 => "Transport endpoint is not connected",
+);
+
+impl core::fmt::Display for SysError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.description())
+    }
+}
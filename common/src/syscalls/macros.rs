@@ -17,6 +17,36 @@ macro_rules! ecall {
             $arg3.into_reg(),
         )
     };
+    ($syscall:expr, $arg1:expr, $arg2:expr, $arg3:expr, $arg4:expr) => {
+        ecall_4(
+            $syscall as usize,
+            $arg1.into_reg(),
+            $arg2.into_reg(),
+            $arg3.into_reg(),
+            $arg4.into_reg(),
+        )
+    };
+    ($syscall:expr, $arg1:expr, $arg2:expr, $arg3:expr, $arg4:expr, $arg5:expr) => {
+        ecall_5(
+            $syscall as usize,
+            $arg1.into_reg(),
+            $arg2.into_reg(),
+            $arg3.into_reg(),
+            $arg4.into_reg(),
+            $arg5.into_reg(),
+        )
+    };
+    ($syscall:expr, $arg1:expr, $arg2:expr, $arg3:expr, $arg4:expr, $arg5:expr, $arg6:expr) => {
+        ecall_6(
+            $syscall as usize,
+            $arg1.into_reg(),
+            $arg2.into_reg(),
+            $arg3.into_reg(),
+            $arg4.into_reg(),
+            $arg5.into_reg(),
+            $arg6.into_reg(),
+        )
+    };
 }
 
 #[macro_export]
@@ -28,6 +58,17 @@ macro_rules! syscalls {
             $($name,)*
         }
 
+        impl Syscalls {
+            pub fn from_number(nr: usize) -> Option<Self> {
+                $(
+                    if nr == Syscalls::$name as usize {
+                        return Some(Syscalls::$name);
+                    }
+                )*
+                None
+            }
+        }
+
         $(
             pub fn $name($($arg_name: $arg_ty),*) -> $ret {
                 let (first, second) = ecall!(Syscalls::$name, $($arg_name),*);
@@ -41,20 +82,24 @@ macro_rules! syscalls {
 
             pub trait KernelSyscalls {
                 $(fn $name(&mut self, $($arg_name: UserspaceArgument<$arg_ty>),*) -> $ret;)*
-                fn dispatch(&mut self, nr: usize, arg0: usize, arg1: usize, arg2: usize) -> (usize, usize) {
+                /// Returns None if the syscall number is unknown.
+                fn dispatch(&mut self, nr: usize, args: [usize; 6]) -> Option<(usize, usize)> {
                     use super::Syscalls;
                     macro_rules! kernel_dispatch_call {
                         ($x:ident,) => { self.$x().into_double_reg() };
-                        ($x:ident, $arg1:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(arg0))).into_double_reg() };
-                        ($x:ident, $arg1:ty, $arg2:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(arg0)), UserspaceArgument::new(<$arg2>::from_reg(arg1))).into_double_reg() };
-                        ($x:ident, $arg1:ty, $arg2:ty, $arg3:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(arg0)), UserspaceArgument::new(<$arg2>::from_reg(arg1)), UserspaceArgument::new(<$arg3>::from_reg(arg2))).into_double_reg() };
+                        ($x:ident, $arg1:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(args[0]))).into_double_reg() };
+                        ($x:ident, $arg1:ty, $arg2:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(args[0])), UserspaceArgument::new(<$arg2>::from_reg(args[1]))).into_double_reg() };
+                        ($x:ident, $arg1:ty, $arg2:ty, $arg3:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(args[0])), UserspaceArgument::new(<$arg2>::from_reg(args[1])), UserspaceArgument::new(<$arg3>::from_reg(args[2]))).into_double_reg() };
+                        ($x:ident, $arg1:ty, $arg2:ty, $arg3:ty, $arg4:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(args[0])), UserspaceArgument::new(<$arg2>::from_reg(args[1])), UserspaceArgument::new(<$arg3>::from_reg(args[2])), UserspaceArgument::new(<$arg4>::from_reg(args[3]))).into_double_reg() };
+                        ($x:ident, $arg1:ty, $arg2:ty, $arg3:ty, $arg4:ty, $arg5:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(args[0])), UserspaceArgument::new(<$arg2>::from_reg(args[1])), UserspaceArgument::new(<$arg3>::from_reg(args[2])), UserspaceArgument::new(<$arg4>::from_reg(args[3])), UserspaceArgument::new(<$arg5>::from_reg(args[4]))).into_double_reg() };
+                        ($x:ident, $arg1:ty, $arg2:ty, $arg3:ty, $arg4:ty, $arg5:ty, $arg6:ty) => { self.$x(UserspaceArgument::new(<$arg1>::from_reg(args[0])), UserspaceArgument::new(<$arg2>::from_reg(args[1])), UserspaceArgument::new(<$arg3>::from_reg(args[2])), UserspaceArgument::new(<$arg4>::from_reg(args[3])), UserspaceArgument::new(<$arg5>::from_reg(args[4])), UserspaceArgument::new(<$arg6>::from_reg(args[5]))).into_double_reg() };
                     }
-                    let enum_value: Syscalls = unsafe { core::mem::transmute(nr) };
-                    match enum_value {
+                    let result = match Syscalls::from_number(nr)? {
                         $(
                             Syscalls::$name => { kernel_dispatch_call!($name, $($arg_ty),*) },
                         )*
-                    }
+                    };
+                    Some(result)
                 }
             }
         }
@@ -1,8 +1,13 @@
-use crate::{ecall, net::UDPDescriptor, syscalls};
+use crate::{
+    ecall,
+    signal::{Signal, SignalSet},
+    syscalls,
+};
 
 use self::syscall_argument::{SyscallArgument, SyscallReturnArgument};
 
 mod ecall;
+mod error;
 mod macros;
 mod syscall_argument;
 pub mod trap_frame;
@@ -10,40 +15,302 @@ pub mod userspace_argument;
 
 use self::{ecall::*, userspace_argument::UserspaceArgument};
 
-#[derive(Debug)]
-#[repr(usize)]
-pub enum SysWaitError {
-    InvalidPid,
+pub use self::error::SysError;
+
+/// Descriptors every process started by the kernel has open.
+pub const STDIN: usize = 0;
+pub const STDOUT: usize = 1;
+pub const STDERR: usize = 2;
+
+/// Describes how a process terminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The process called `sys_exit` with the given status.
+    Exited(isize),
+    /// The process caused an exception with the given exception code.
+    Crashed(usize),
+    /// The process was terminated by a signal.
+    Signaled(Signal),
+}
+
+impl ExitStatus {
+    pub fn is_success(&self) -> bool {
+        *self == ExitStatus::Exited(0)
+    }
+}
+
+impl core::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExitStatus::Exited(status) => write!(f, "exited with status {status}"),
+            ExitStatus::Crashed(code) => write!(f, "crashed with exception code {code}"),
+            ExitStatus::Signaled(signal) => write!(f, "killed by {signal}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildExit {
+    pub pid: u64,
+    pub status: ExitStatus,
+}
+
+/// The descriptors of the two ends of a pipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipeDescriptors {
+    pub read: usize,
+    pub write: usize,
 }
 
-#[derive(Debug)]
-#[repr(usize)]
-pub enum SysExecuteError {
-    InvalidPtr,
-    InvalidProgram,
+/// Passed to sys_channel_send if the message carries no descriptor.
+pub const NO_DESCRIPTOR: usize = usize::MAX;
+
+/// Describes a message received from a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceivedMessage {
+    pub length: usize,
+    // The receiver got its own descriptor of the file the sender attached
+    pub descriptor: Option<usize>,
+}
+
+/// What a process listed by sys_list_processes is doing.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessState {
+    // One of its threads is on the cpu
+    Running,
+    // At least one thread is runnable
+    #[default]
+    Runnable,
+    // All threads wait, e.g. for input or a child
+    Waiting,
+    // Exited, but the parent did not collect the exit status yet
+    Zombie,
+}
+
+/// Describes a process listed by sys_list_processes.
+/// The layout has no padding such that the kernel can copy it as bytes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProcessInfo {
+    pub pid: u64,
+    pub parent_pid: u64,
+    // Added up over all threads (in milliseconds)
+    pub user_time: u64,
+    pub kernel_time: u64,
+    pub resident_pages: u64,
+    // Zero padded and truncated if too long
+    name: [u8; 23],
+    pub state: ProcessState,
+}
+
+impl ProcessInfo {
+    pub fn new(
+        pid: u64,
+        parent_pid: u64,
+        name: &str,
+        state: ProcessState,
+        times: CpuTimes,
+        resident_pages: u64,
+    ) -> Self {
+        let mut info = Self {
+            pid,
+            parent_pid,
+            user_time: times.user,
+            kernel_time: times.kernel,
+            resident_pages,
+            name: [0; 23],
+            state,
+        };
+        let mut length = name.len().min(info.name.len());
+        while !name.is_char_boundary(length) {
+            length -= 1;
+        }
+        info.name[..length].copy_from_slice(&name.as_bytes()[..length]);
+        info
+    }
+
+    pub fn name(&self) -> &str {
+        let length = self
+            .name
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..length]).unwrap_or("?")
+    }
+}
+
+/// Cpu time (in milliseconds) spent in userspace and in the kernel.
+/// The layout has no padding such that the kernel can copy it as bytes.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuTimes {
+    pub user: u64,
+    pub kernel: u64,
+}
+
+impl core::ops::Sub for CpuTimes {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            user: self.user - other.user,
+            kernel: self.kernel - other.kernel,
+        }
+    }
+}
+
+/// Filled by sys_times.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProcessTimes {
+    // Added up over all threads
+    pub own: CpuTimes,
+    // Added up over all children whose exit status was collected and their children
+    pub children: CpuTimes,
+}
+
+/// Access rights of memory pages.
+/// The bits correspond to the R, W and X bits of a page table entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryProtection(usize);
+
+impl MemoryProtection {
+    pub const READ_ONLY: MemoryProtection = MemoryProtection(0b001);
+    pub const READ_WRITE: MemoryProtection = MemoryProtection(0b011);
+    pub const EXECUTE_ONLY: MemoryProtection = MemoryProtection(0b100);
+    pub const READ_EXECUTE: MemoryProtection = MemoryProtection(0b101);
+    pub const READ_WRITE_EXECUTE: MemoryProtection = MemoryProtection(0b111);
+
+    /// Returns None for combinations the hardware does not support, e.g. write without read.
+    pub fn new(bits: usize) -> Option<Self> {
+        match bits {
+            0b001 | 0b011 | 0b100 | 0b101 | 0b111 => Some(Self(bits)),
+            _ => None,
+        }
+    }
+
+    pub fn bits(&self) -> usize {
+        self.0
+    }
+}
+
+/// Passed to sys_setrlimit to remove a limit.
+pub const UNLIMITED: usize = usize::MAX;
+
+/// A resource whose usage is limited per process.
+/// Children inherit the limits on fork and execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resource(usize);
+
+impl Resource {
+    /// Pages reserved by sys_mmap_pages and sys_shm_map
+    pub const MAPPED_PAGES: Resource = Resource(0);
+    /// Pages the stack of the main thread can grow to (at most 16384, larger values are clamped).
+    /// Cannot be set below the pages the stack already uses.
+    pub const STACK_PAGES: Resource = Resource(1);
+    /// New descriptors are always lower than the limit
+    pub const DESCRIPTORS: Resource = Resource(2);
+    /// Milliseconds of cpu time. Exceeding it raises SIGXCPU and one second later SIGKILL
+    pub const CPU_TIME: Resource = Resource(3);
+    /// Children which did not exit yet
+    pub const CHILDREN: Resource = Resource(4);
+
+    pub const COUNT: usize = Self::CHILDREN.0 + 1;
+
+    pub fn new(number: usize) -> Option<Self> {
+        (number < Self::COUNT).then_some(Self(number))
+    }
+
+    pub fn number(&self) -> usize {
+        self.0
+    }
 }
 
-#[derive(Debug)]
-#[repr(usize)]
-pub enum SysSocketError {
-    PortAlreadyUsed,
-    InvalidPtr,
-    InvalidDescriptor,
-    NoReceiveIPYet,
+/// Filled by sys_getrlimit.
+/// The soft limit is enforced and can be raised up to the hard limit.
+/// The hard limit can only ever be lowered.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimit {
+    pub soft: usize,
+    pub hard: usize,
 }
 
 syscalls!(
-    sys_write_char(c: char) -> ();
-    sys_read_input() -> Option<u8>;
-    sys_read_input_wait() -> u8;
+    // Blocks until at least one byte is available and returns 0 at the end of the input
+    sys_read(descriptor: usize, buffer: &mut u8, length: usize) -> Result<usize, SysError>;
+    // Might write less than length. Messages which do not fit into one write fail instead
+    sys_write(descriptor: usize, buffer: &u8, length: usize) -> Result<usize, SysError>;
     sys_exit(status: isize) -> ();
     // TODO: Implement slice as argument using a wrapper
-    sys_execute(name: &u8, length: usize) -> Result<u64, SysExecuteError>;
-    sys_wait(pid: u64) -> Result<(), SysWaitError>;
-    sys_mmap_pages(number_of_pages: usize) -> *mut u8;
-    sys_open_udp_socket(port: u16) -> Result<UDPDescriptor, SysSocketError>;
-    sys_write_back_udp_socket(descriptor: UDPDescriptor, buffer: &u8, length: usize) -> Result<usize, SysSocketError>;
-    sys_read_udp_socket(descriptor: UDPDescriptor, buffer: &mut u8, length: usize) -> Result<usize, SysSocketError>;
+    // data contains null terminated strings: first argc arguments followed by the environment
+    sys_execute(data: &u8, length: usize, argc: usize) -> Result<u64, SysError>;
+    // pid 0 waits for any child
+    sys_wait(pid: u64, non_blocking: bool) -> Result<ChildExit, SysError>;
+    sys_mmap_pages(number_of_pages: usize) -> Result<*mut u8, SysError>;
+    // Returns a descriptor. Writing to it answers the sender of the last received packet
+    sys_open_udp_socket(port: u16) -> Result<usize, SysError>;
     sys_panic() -> ();
     sys_print_programs() -> ();
+    sys_fork() -> Result<u64, SysError>;
+    sys_kill(pid: u64, signal: Signal) -> Result<(), SysError>;
+    // handler is either SIG_DFL, SIG_IGN or the address of the handler
+    // The handler returns to restorer which must call sys_sigreturn without touching the stack
+    sys_sigaction(signal: Signal, handler: usize, restorer: usize) -> Result<(), SysError>;
+    // Returns the previously blocked signals
+    sys_sigprocmask(block: SignalSet, unblock: SignalSet) -> SignalSet;
+    sys_sigreturn() -> ();
+    // Ctrl+C sends SIGINT to the foreground process
+    sys_set_foreground(pid: u64) -> Result<(), SysError>;
+    sys_sleep(milliseconds: u64) -> ();
+    // Lets other runnable processes run first
+    sys_yield() -> ();
+    // Applies to all threads of the process. pid 0 changes the priority of the calling process;
+    // nice ranges from -20 to 19
+    sys_setpriority(pid: u64, nice: isize) -> Result<(), SysError>;
+    // The new thread starts at entry with arg as its first argument
+    sys_thread_create(entry: usize, stack: usize, arg: usize) -> u64;
+    // Exiting the main thread terminates the whole process
+    sys_thread_exit(value: isize) -> ();
+    // Waits until the thread exited and returns its exit value
+    sys_thread_join(tid: u64) -> Result<isize, SysError>;
+    // Blocks until woken up if the u32 at address still has the expected value
+    sys_futex_wait(address: usize, expected: u32) -> Result<(), SysError>;
+    // Returns the number of woken up threads
+    sys_futex_wake(address: usize, count: usize) -> Result<usize, SysError>;
+    // The pages must have been mapped by sys_mmap_pages
+    sys_munmap(address: usize, number_of_pages: usize) -> Result<(), SysError>;
+    sys_mprotect(address: usize, number_of_pages: usize, protection: MemoryProtection) -> Result<(), SysError>;
+    sys_close(descriptor: usize) -> Result<(), SysError>;
+    // Makes new_descriptor refer to the same object as old_descriptor and closes it before if necessary
+    sys_dup2(old_descriptor: usize, new_descriptor: usize) -> Result<usize, SysError>;
+    // Reading blocks while the pipe is empty and writing while it is full
+    sys_pipe() -> Result<PipeDescriptors, SysError>;
+    // Returns a descriptor of zeroed memory which is shared with every process mapping it
+    sys_shm_create(number_of_pages: usize) -> Result<usize, SysError>;
+    // Maps the first pages of the shared memory. sys_munmap removes the mapping again
+    sys_shm_map(descriptor: usize, number_of_pages: usize) -> Result<*mut u8, SysError>;
+    // Returns a descriptor of a message queue. Every process holding it can send and receive
+    sys_channel_create() -> Result<usize, SysError>;
+    // Blocks while the queue is full. attached is a descriptor passed along or NO_DESCRIPTOR.
+    // Channels cannot be attached, since a channel carrying itself would never be freed
+    sys_channel_send(descriptor: usize, buffer: &u8, length: usize, attached: usize) -> Result<(), SysError>;
+    // Blocks until a message arrives. Fails without removing the message if the buffer is too small
+    sys_channel_receive(descriptor: usize, buffer: &mut u8, length: usize) -> Result<ReceivedMessage, SysError>;
+    // Fills the buffer with up to count processes and returns the number of all processes
+    sys_list_processes(buffer: &mut ProcessInfo, count: usize) -> Result<usize, SysError>;
+    // Returns the pid of the whole process, i.e. of its main thread
+    sys_getpid() -> u64;
+    // Orphans get init as their new parent
+    sys_getppid() -> u64;
+    // Returns the milliseconds since boot
+    sys_times(times: &mut ProcessTimes) -> Result<u64, SysError>;
+    // Limits are a number of the resource's unit or UNLIMITED. Fails with InvalidArgument if
+    // soft exceeds hard and with PermissionDenied if hard exceeds the current hard limit
+    sys_setrlimit(resource: Resource, soft: usize, hard: usize) -> Result<(), SysError>;
+    sys_getrlimit(resource: Resource, limit: &mut ResourceLimit) -> Result<(), SysError>;
+    // Returns the lowest free descriptor which refers to the same object. If close_on_execute
+    // is set, programs started with sys_execute do not inherit the new descriptor
+    sys_dup(descriptor: usize, close_on_execute: bool) -> Result<usize, SysError>;
 );
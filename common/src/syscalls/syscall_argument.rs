@@ -1,6 +1,8 @@
-use crate::net::UDPDescriptor;
+use crate::signal::{Signal, SignalSet};
 
-use super::{SysExecuteError, SysSocketError, SysWaitError};
+use super::{
+    ChildExit, ExitStatus, MemoryProtection, PipeDescriptors, ReceivedMessage, Resource, SysError,
+};
 
 pub trait SyscallArgument {
     fn into_reg(self) -> usize;
@@ -56,13 +58,37 @@ impl<T: SyscallArgument> SyscallReturnArgument for Option<T> {
     }
 }
 
+// A child pid is never 0, therefore we can use it to distinguish the error case
+impl SyscallReturnArgument for Result<ChildExit, SysError> {
+    fn into_double_reg(self) -> (usize, usize) {
+        match self {
+            Ok(ChildExit { pid, status }) => (pid as usize, status.into_reg()),
+            Err(error) => (0, error.into_reg()),
+        }
+    }
+
+    fn from_double_reg(first: usize, second: usize) -> Self {
+        if first == 0 {
+            Err(SysError::from_reg(second))
+        } else {
+            Ok(ChildExit {
+                pid: first as u64,
+                status: ExitStatus::from_reg(second),
+            })
+        }
+    }
+}
+
 impl SyscallArgument for char {
     fn into_reg(self) -> usize {
         self as usize
     }
 
     fn from_reg(value: usize) -> Self {
-        value as u8 as char
+        u32::try_from(value)
+            .ok()
+            .and_then(char::from_u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER)
     }
 }
 
@@ -76,6 +102,16 @@ impl SyscallArgument for u8 {
     }
 }
 
+impl SyscallArgument for bool {
+    fn into_reg(self) -> usize {
+        self as usize
+    }
+
+    fn from_reg(value: usize) -> Self {
+        value != 0
+    }
+}
+
 impl SyscallArgument for u16 {
     fn into_reg(self) -> usize {
         self as usize
@@ -86,6 +122,16 @@ impl SyscallArgument for u16 {
     }
 }
 
+impl SyscallArgument for u32 {
+    fn into_reg(self) -> usize {
+        self as usize
+    }
+
+    fn from_reg(value: usize) -> Self {
+        value as u32
+    }
+}
+
 impl SyscallArgument for isize {
     fn into_reg(self) -> usize {
         self as usize
@@ -154,42 +200,102 @@ impl<T> SyscallArgument for *mut T {
     }
 }
 
-impl SyscallArgument for SysWaitError {
+// The lowest byte contains the kind of exit and the rest the payload
+impl SyscallArgument for ExitStatus {
     fn into_reg(self) -> usize {
-        self as usize
+        match self {
+            ExitStatus::Exited(status) => (status as usize) << 8,
+            ExitStatus::Crashed(code) => (code << 8) | 1,
+            ExitStatus::Signaled(signal) => (signal.into_reg() << 8) | 2,
+        }
+    }
+
+    fn from_reg(value: usize) -> Self {
+        match value & 0xff {
+            0 => ExitStatus::Exited((value as isize) >> 8),
+            1 => ExitStatus::Crashed(value >> 8),
+            2 => ExitStatus::Signaled(Signal::from_reg(value >> 8)),
+            _ => panic!("Invalid exit status {value:#x}"),
+        }
+    }
+}
+
+// Descriptors are small, therefore both fit into one register
+impl SyscallArgument for PipeDescriptors {
+    fn into_reg(self) -> usize {
+        (self.read as u32 as usize) | (self.write << 32)
     }
 
     fn from_reg(value: usize) -> Self {
-        unsafe { core::mem::transmute(value) }
+        PipeDescriptors {
+            read: value as u32 as usize,
+            write: value >> 32,
+        }
     }
 }
 
-impl SyscallArgument for SysExecuteError {
+// Messages are small and the descriptor is shifted by one to encode None as 0
+impl SyscallArgument for ReceivedMessage {
     fn into_reg(self) -> usize {
-        self as usize
+        let descriptor = self.descriptor.map_or(0, |descriptor| descriptor + 1);
+        (self.length as u32 as usize) | (descriptor << 32)
     }
 
     fn from_reg(value: usize) -> Self {
-        unsafe { core::mem::transmute(value) }
+        ReceivedMessage {
+            length: value as u32 as usize,
+            descriptor: (value >> 32).checked_sub(1),
+        }
     }
 }
 
-impl SyscallArgument for UDPDescriptor {
+// The kernel must check the signal number
+impl SyscallArgument for Signal {
     fn into_reg(self) -> usize {
-        self.get() as usize
+        self.0
     }
 
     fn from_reg(value: usize) -> Self {
-        UDPDescriptor::new(value as u64)
+        Signal(value)
     }
 }
 
-impl SyscallArgument for SysSocketError {
+impl SyscallArgument for SignalSet {
     fn into_reg(self) -> usize {
-        self as usize
+        self.bits() as usize
+    }
+
+    fn from_reg(value: usize) -> Self {
+        SignalSet::from_bits(value as u64)
+    }
+}
+
+impl SyscallArgument for MemoryProtection {
+    fn into_reg(self) -> usize {
+        self.0
+    }
+
+    fn from_reg(value: usize) -> Self {
+        MemoryProtection(value)
+    }
+}
+
+impl SyscallArgument for Resource {
+    fn into_reg(self) -> usize {
+        self.0
+    }
+
+    fn from_reg(value: usize) -> Self {
+        Resource(value)
+    }
+}
+
+impl SyscallArgument for SysError {
+    fn into_reg(self) -> usize {
+        self.code()
     }
 
     fn from_reg(value: usize) -> Self {
-        unsafe { core::mem::transmute(value) }
+        SysError::from_code(value).expect("Kernel must return a known error code")
     }
 }
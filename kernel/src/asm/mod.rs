@@ -2,3 +2,4 @@ use core::arch::global_asm;
 
 global_asm!(include_str!("boot.S"));
 global_asm!(include_str!("trap.S"));
+global_asm!(include_str!("user_access.S"));
@@ -29,6 +29,16 @@ pub unsafe fn write_satp_and_fence(satp_val: usize) {
     }
 }
 
+pub fn flush_tlb() {
+    if cfg!(miri) {
+        return;
+    }
+
+    unsafe {
+        asm!("sfence.vma");
+    }
+}
+
 pub fn read_satp() -> usize {
     if cfg!(miri) {
         return 0;
@@ -65,12 +75,6 @@ pub fn wait_for_interrupt() {
 const SIE_STIE: usize = 5;
 const SSTATUS_SPP: usize = 8;
 
-pub fn is_timer_enabled() -> bool {
-    let sie: usize;
-    unsafe { asm!("csrr {}, sie", out(reg) sie) }
-    (sie & (1 << SIE_STIE)) > 0
-}
-
 pub fn disable_timer_interrupt() {
     unsafe {
         asm!("
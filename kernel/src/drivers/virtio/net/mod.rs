@@ -13,8 +13,10 @@ use crate::{
         util::{is_power_of_2_or_zero, BufferExtension, ByteInterpretable},
         MMIO,
     },
+    memory,
     net::mac::MacAddress,
     pci::PCIDevice,
+    warn,
 };
 use alloc::vec::Vec;
 
@@ -262,8 +264,16 @@ impl NetworkDevice {
             assert!(net_hdr.gso_type == VIRTIO_NET_HDR_GSO_NONE);
             assert!(net_hdr.flags == 0);
 
-            let data = data_bytes.to_vec();
-            received_packets.push(data);
+            // Packets are dropped instead of taking memory from the reserve of the kernel
+            match memory::try_zeroed_buffer(data_bytes.len()) {
+                Ok(mut data) => {
+                    data.copy_from_slice(data_bytes);
+                    received_packets.push(data);
+                }
+                Err(_) => {
+                    warn!("Dropping received packet because memory is short");
+                }
+            }
 
             // Put buffer back into receive queue
             self.receive_queue
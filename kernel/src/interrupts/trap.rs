@@ -1,28 +1,59 @@
-use super::trap_cause::{exception::ENVIRONMENT_CALL_FROM_U_MODE, InterruptCause};
+use super::trap_cause::{
+    exception::{
+        ENVIRONMENT_CALL_FROM_U_MODE, INSTRUCTION_PAGE_FAULT, LOAD_PAGE_FAULT, STORE_AMO_PAGE_FAULT,
+    },
+    InterruptCause,
+};
 use crate::{
     cpu::{self},
     debug,
     interrupts::plic::{self, InterruptSource},
     io::{stdin_buf::STDIN_BUFFER, uart},
-    memory::linker_information::LinkerInformation,
+    memory::{self, linker_information::LinkerInformation},
+    println,
     processes::{
+        address_space::StackFault,
         process::ProcessState,
+        process_table::ProcessRef,
         scheduler::{self},
+        timer,
     },
     syscalls::{self},
     warn,
 };
-use common::syscalls::trap_frame::{Register, TrapFrame};
+use common::{
+    signal::Signal,
+    syscalls::{
+        trap_frame::{Register, TrapFrame},
+        ExitStatus, SysError,
+    },
+};
 use core::panic;
 
 #[no_mangle]
 extern "C" fn handle_timer_interrupt() {
-    scheduler::THE.lock().schedule();
+    let expired = timer::take_expired_deadlines();
+    scheduler::THE.with_lock(|mut s| {
+        s.account_trap_entry();
+        for pid in expired.wakeups {
+            s.wake_up(pid);
+        }
+        // The kernel had to use its reserve, so userspace must give memory back
+        if memory::is_starved() {
+            s.kill_largest_process();
+        }
+        if expired.quantum_expired || s.is_idle() {
+            s.schedule();
+        }
+        s.deliver_signals();
+        s.account_trap_exit();
+    });
 }
 
 #[no_mangle]
 fn handle_external_interrupt() {
     debug!("External interrupt occurred!");
+    scheduler::THE.lock().account_trap_entry();
     let plic_interrupt = plic::get_next_pending().expect("There should be a pending interrupt.");
     assert!(
         plic_interrupt == InterruptSource::Uart,
@@ -38,27 +69,101 @@ fn handle_external_interrupt() {
         4 => crate::debugging::dump_current_state(),
         _ => STDIN_BUFFER.lock().push(input),
     }
+
+    scheduler::THE.with_lock(|mut s| {
+        s.deliver_signals();
+        s.account_trap_exit();
+    });
 }
 
 fn handle_syscall(sepc: usize, trap_frame: &mut TrapFrame) {
     let nr = trap_frame[Register::a0];
-    let arg1 = trap_frame[Register::a1];
-    let arg2 = trap_frame[Register::a2];
-    let arg3 = trap_frame[Register::a3];
-    let ret = syscalls::handle_syscall(nr, arg1, arg2, arg3);
+    let args = [
+        trap_frame[Register::a1],
+        trap_frame[Register::a2],
+        trap_frame[Register::a3],
+        trap_frame[Register::a4],
+        trap_frame[Register::a5],
+        trap_frame[Register::a6],
+    ];
+    let ret = syscalls::handle_syscall(nr, args);
     if let Some((ret1, ret2)) = ret {
         trap_frame[Register::a0] = ret1;
         trap_frame[Register::a1] = ret2;
         cpu::write_sepc(sepc + 4); // Skip the ecall instruction
     }
-    // In case our current process was set to waiting state we need to reschedule
+    // In case our current process was set to waiting state or yielded we need to reschedule
     scheduler::THE.with_lock(|mut s| {
-        if s.get_current_process().lock().get_state() == ProcessState::Waiting {
+        if s.is_yield_requested()
+            || s.get_current_process().lock().get_state() == ProcessState::Waiting
+        {
             s.schedule();
         }
     });
 }
 
+fn handle_page_fault(cause: InterruptCause, stval: usize, sepc: usize, trap_frame: &mut TrapFrame) {
+    if cpu::is_in_kernel_mode() {
+        handle_unhandled_exception(cause, stval, sepc, trap_frame);
+        return;
+    }
+
+    let mut scheduler = scheduler::THE.lock();
+    let stack_fault = match resolve_lazy_page(scheduler.get_current_process(), cause, stval) {
+        Ok(true) => return,
+        Ok(false) => scheduler
+            .get_current_process()
+            .lock()
+            .address_space()
+            .handle_stack_fault(stval),
+        Err(error) => Err(error),
+    };
+    match stack_fault {
+        Ok(Some(StackFault::Grown)) => {}
+        Ok(Some(StackFault::Overflow)) => {
+            scheduler.get_current_process().with_lock(|p| {
+                println!(
+                    "Stack overflow in process {} (PID: {})",
+                    p.get_name(),
+                    p.get_pid()
+                );
+            });
+            scheduler.kill_current_process(ExitStatus::Signaled(Signal::SIGSEGV));
+            scheduler.schedule();
+        }
+        Ok(None) => {
+            drop(scheduler);
+            handle_unhandled_exception(cause, stval, sepc, trap_frame);
+        }
+        // The access is retried once the memory of the killed process is freed
+        Err(_) => {
+            if !scheduler.kill_largest_process() {
+                scheduler.kill_current_process(ExitStatus::Signaled(Signal::SIGKILL));
+                scheduler.schedule();
+            }
+        }
+    }
+}
+
+/// Backs copy-on-write and mmap pages with memory on their first write or access.
+/// Returns false if the address does not belong to such a page.
+fn resolve_lazy_page(
+    process: &ProcessRef,
+    cause: InterruptCause,
+    stval: usize,
+) -> Result<bool, SysError> {
+    let mut process = process.lock();
+    // Writes to pages shared after a fork are resolved by copying the page
+    if cause.get_exception_code() == STORE_AMO_PAGE_FAULT
+        && process.handle_copy_on_write_fault(stval)?
+    {
+        return Ok(true);
+    }
+    // Pages of mmap regions are only backed by memory once they are accessed
+    let resolved = process.address_space().handle_mmap_fault(stval);
+    resolved
+}
+
 fn warn_on_stackoverflow(cause: InterruptCause, stval: usize) {
     if cause.is_stack_overflow(stval) {
         let guard_range = LinkerInformation::__start_stack_overflow_guard()
@@ -70,12 +175,35 @@ fn warn_on_stackoverflow(cause: InterruptCause, stval: usize) {
     }
 }
 
+fn kill_faulting_process(cause: InterruptCause, stval: usize, sepc: usize) {
+    let mut scheduler = scheduler::THE.lock();
+    scheduler.get_current_process().with_lock(|p| {
+        println!(
+            "Process crashed!\nName: {}\nException code: {}\nstval: 0x{:x}\nsepc: 0x{:x}\nProcess name: {}\nPID: {}",
+            cause.get_reason(),
+            cause.get_exception_code(),
+            stval,
+            sepc,
+            p.get_name(),
+            p.get_pid()
+        );
+    });
+    scheduler.kill_current_process(ExitStatus::Crashed(cause.get_exception_code()));
+    scheduler.schedule();
+}
+
 fn handle_unhandled_exception(
     cause: InterruptCause,
     stval: usize,
     sepc: usize,
     trap_frame: &mut TrapFrame,
 ) {
+    // A fault in userspace only takes down the faulting process
+    if !cpu::is_in_kernel_mode() {
+        kill_faulting_process(cause, stval, sepc);
+        return;
+    }
+
     let message= scheduler::THE.lock().get_current_process().with_lock(|p| {
         format!(
             "Unhandled exception!\nName: {}\nException code: {}\nstval: 0x{:x}\nsepc: 0x{:x}\nFrom Userspace: {}\nProcess name: {}\nTrap Frame: {:?}",
@@ -83,7 +211,7 @@ fn handle_unhandled_exception(
             cause.get_exception_code(),
             stval,
             sepc,
-            p.get_page_table().is_userspace_address(sepc),
+            p.address_space().page_table().is_userspace_address(sepc),
             p.get_name(),
             trap_frame
         )
@@ -98,11 +226,19 @@ extern "C" fn handle_exception(
     sepc: usize,
     trap_frame: &mut TrapFrame,
 ) {
+    scheduler::THE.lock().account_trap_entry();
     warn_on_stackoverflow(cause, stval);
     match cause.get_exception_code() {
         ENVIRONMENT_CALL_FROM_U_MODE => handle_syscall(sepc, trap_frame),
+        INSTRUCTION_PAGE_FAULT | LOAD_PAGE_FAULT | STORE_AMO_PAGE_FAULT => {
+            handle_page_fault(cause, stval, sepc, trap_frame)
+        }
         _ => handle_unhandled_exception(cause, stval, sepc, trap_frame),
     }
+    scheduler::THE.with_lock(|mut s| {
+        s.deliver_signals();
+        s.account_trap_exit();
+    });
 }
 
 #[no_mangle]
@@ -0,0 +1,254 @@
+use alloc::{
+    collections::{BTreeSet, VecDeque},
+    vec::Vec,
+};
+use common::{mutex::Mutex, syscalls::SysError};
+
+use crate::{
+    memory,
+    processes::{process::Pid, scheduler},
+};
+
+use super::file::{release, File, Readiness, SharedFile};
+
+pub const MAX_MESSAGE_SIZE: usize = 4096;
+const MAX_MESSAGES: usize = 64;
+
+struct Message {
+    data: Vec<u8>,
+    file: Option<SharedFile>,
+}
+
+impl Drop for Message {
+    fn drop(&mut self) {
+        // The file is closed if the message was its last reference
+        if let Some(file) = self.file.take() {
+            release(file);
+        }
+    }
+}
+
+struct Queue {
+    messages: VecDeque<Message>,
+    // Receivers wait for messages and senders for free space
+    wakeup_queue: BTreeSet<Pid>,
+}
+
+/// Queue of messages which keeps the boundaries between the messages.
+/// Every process with a descriptor of the channel can send and receive.
+pub struct Channel {
+    queue: Mutex<Queue>,
+}
+
+impl Channel {
+    pub fn new() -> Self {
+        Self {
+            queue: Mutex::new(Queue {
+                messages: VecDeque::new(),
+                wakeup_queue: BTreeSet::new(),
+            }),
+        }
+    }
+}
+
+impl Queue {
+    fn wake_up_waiting(&mut self) {
+        // The woken up processes restart their syscall and check the queue again
+        for pid in core::mem::take(&mut self.wakeup_queue) {
+            scheduler::wake_up_deferred(pid);
+        }
+    }
+}
+
+impl File for Channel {
+    /// Receives one message. A file carried by the message is closed.
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, SysError> {
+        // The message holds the last reference and closes the file when it is dropped
+        self.receive_message(buffer, &mut |_| Ok(()))
+    }
+
+    /// Sends the data as one message.
+    fn write(&self, data: &[u8]) -> Result<usize, SysError> {
+        self.send_message(data, None).map(|_| data.len())
+    }
+
+    fn keeps_message_boundaries(&self) -> bool {
+        true
+    }
+
+    fn carries_files(&self) -> bool {
+        true
+    }
+
+    fn poll(&self) -> Readiness {
+        let queue = self.queue.lock();
+        Readiness {
+            readable: !queue.messages.is_empty(),
+            writable: queue.messages.len() < MAX_MESSAGES,
+        }
+    }
+
+    fn register_wakeup(&self, pid: Pid) {
+        self.queue.lock().wakeup_queue.insert(pid);
+    }
+
+    fn send_message(&self, data: &[u8], file: Option<SharedFile>) -> Result<(), SysError> {
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(SysError::MessageTooLong);
+        }
+        if file.as_ref().is_some_and(|file| file.carries_files()) {
+            return Err(SysError::InvalidArgument);
+        }
+        // Userspace decides how many messages are queued, therefore memory might be short
+        let mut copy = memory::try_zeroed_buffer(data.len())?;
+        copy.copy_from_slice(data);
+        let mut queue = self.queue.lock();
+        if queue.messages.len() >= MAX_MESSAGES {
+            return Err(SysError::WouldBlock);
+        }
+        queue
+            .messages
+            .try_reserve(1)
+            .map_err(|_| SysError::OutOfMemory)?;
+        queue.messages.push_back(Message { data: copy, file });
+        queue.wake_up_waiting();
+        Ok(())
+    }
+
+    fn receive_message(
+        &self,
+        buffer: &mut [u8],
+        accept: &mut dyn FnMut(SharedFile) -> Result<(), SysError>,
+    ) -> Result<usize, SysError> {
+        let mut queue = self.queue.lock();
+        let message = queue.messages.front().ok_or(SysError::WouldBlock)?;
+        let length = message.data.len();
+        if length > buffer.len() {
+            return Err(SysError::MessageTooLong);
+        }
+        if let Some(file) = &message.file {
+            accept(file.clone())?;
+        }
+        let message = queue.messages.pop_front().expect("Message must exist");
+        buffer[..length].copy_from_slice(&message.data);
+        queue.wake_up_waiting();
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{sync::Arc, vec};
+    use common::syscalls::SysError;
+
+    use crate::{
+        io::{
+            file::{File, SharedFile},
+            pipe,
+        },
+        processes::fd_table::FileDescriptorTable,
+    };
+
+    use super::{Channel, MAX_MESSAGES, MAX_MESSAGE_SIZE};
+
+    #[test_case]
+    fn messages_keep_their_boundaries() {
+        let channel = Channel::new();
+        assert!(!channel.poll().readable);
+
+        assert_eq!(channel.write(&[1, 2]), Ok(2));
+        assert_eq!(channel.write(&[3]), Ok(1));
+        assert!(channel.poll().readable);
+
+        let mut buffer = [0; 8];
+        assert_eq!(channel.read(&mut buffer), Ok(2));
+        assert_eq!(buffer[..2], [1, 2]);
+        assert_eq!(channel.read(&mut buffer), Ok(1));
+        assert_eq!(buffer[0], 3);
+        assert!(!channel.poll().readable);
+    }
+
+    #[test_case]
+    fn too_small_buffer_keeps_message() {
+        let channel = Channel::new();
+        channel.write(&[1, 2, 3]).unwrap();
+
+        let mut buffer = [0; 2];
+        assert_eq!(channel.read(&mut buffer), Err(SysError::MessageTooLong));
+        let mut buffer = [0; 3];
+        assert_eq!(channel.read(&mut buffer), Ok(3));
+
+        let data = vec![0; MAX_MESSAGE_SIZE + 1];
+        assert_eq!(channel.write(&data), Err(SysError::MessageTooLong));
+    }
+
+    #[test_case]
+    fn full_channel_is_not_writable() {
+        let channel = Channel::new();
+        for _ in 0..MAX_MESSAGES {
+            channel.write(&[0]).unwrap();
+        }
+        assert!(!channel.poll().writable);
+        assert_eq!(channel.write(&[0]), Err(SysError::WouldBlock));
+    }
+
+    #[test_case]
+    fn message_carries_file() {
+        let channel = Channel::new();
+        let (reader, writer) = pipe::create();
+        channel.send_message(&[1], Some(writer)).unwrap();
+
+        let mut buffer = [0; 1];
+        let mut file = None;
+        let length = channel.receive_message(&mut buffer, &mut |attached| {
+            file = Some(attached);
+            Ok(())
+        });
+        assert_eq!(length, Ok(1));
+        let file: SharedFile = file.expect("Message must carry the file");
+        assert_eq!(file.write(&[42]), Ok(1));
+        assert_eq!(reader.read(&mut buffer), Ok(1));
+        assert_eq!(buffer, [42]);
+        assert_eq!(Arc::strong_count(&file), 1);
+    }
+
+    #[test_case]
+    fn message_stays_queued_if_descriptor_table_is_full() {
+        let channel = Channel::new();
+        let (_reader, writer) = pipe::create();
+        channel.send_message(&[1], Some(writer)).unwrap();
+
+        let mut descriptors = FileDescriptorTable::new();
+        descriptors.set_limit(0);
+        let mut buffer = [0; 1];
+        let mut accept = |file| descriptors.open(file).map(|_| ());
+        assert_eq!(
+            channel.receive_message(&mut buffer, &mut accept),
+            Err(SysError::TooManyFiles)
+        );
+        assert!(channel.poll().readable);
+
+        descriptors.set_limit(1);
+        let mut accept = |file| descriptors.open(file).map(|_| ());
+        assert_eq!(channel.receive_message(&mut buffer, &mut accept), Ok(1));
+        assert!(descriptors.get(0).is_ok());
+        assert!(!channel.poll().readable);
+    }
+
+    #[test_case]
+    fn channels_cannot_be_carried() {
+        let channel: SharedFile = Arc::new(Channel::new());
+        assert_eq!(
+            channel.send_message(&[1], Some(channel.clone())),
+            Err(SysError::InvalidArgument)
+        );
+        let other: SharedFile = Arc::new(Channel::new());
+        assert_eq!(
+            channel.send_message(&[1], Some(other.clone())),
+            Err(SysError::InvalidArgument)
+        );
+        assert!(!channel.poll().readable);
+        assert_eq!(Arc::strong_count(&channel), 1);
+        assert_eq!(Arc::strong_count(&other), 1);
+    }
+}
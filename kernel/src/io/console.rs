@@ -0,0 +1,43 @@
+use common::syscalls::SysError;
+
+use crate::processes::process::Pid;
+
+use super::{
+    file::{File, Readiness},
+    stdin_buf::STDIN_BUFFER,
+    uart::QEMU_UART,
+};
+
+/// Reads the input from the UART and writes to it.
+pub struct Console;
+
+impl File for Console {
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, SysError> {
+        let mut stdin = STDIN_BUFFER.lock();
+        let mut count = 0;
+        for byte in buffer.iter_mut() {
+            let Some(input) = stdin.pop() else {
+                break;
+            };
+            *byte = input;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize, SysError> {
+        QEMU_UART.lock().write_bytes(data);
+        Ok(data.len())
+    }
+
+    fn poll(&self) -> Readiness {
+        Readiness {
+            readable: !STDIN_BUFFER.lock().is_empty(),
+            writable: true,
+        }
+    }
+
+    fn register_wakeup(&self, pid: Pid) {
+        STDIN_BUFFER.lock().register_wakeup(pid);
+    }
+}
@@ -0,0 +1,69 @@
+use alloc::sync::Arc;
+use common::syscalls::SysError;
+
+use crate::{memory::page::PinnedHeapPages, processes::process::Pid};
+
+pub type SharedFile = Arc<dyn File>;
+
+/// What can be done with a file without blocking.
+/// Operations which fail immediately, e.g. reading the writing end of a pipe, count as ready.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Readiness {
+    pub readable: bool,
+    pub writable: bool,
+}
+
+/// A kernel object a process can access through a file descriptor.
+pub trait File: Send + Sync {
+    /// Returns 0 at the end of the data. Must only be called if the file is readable.
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, SysError>;
+
+    fn write(&self, data: &[u8]) -> Result<usize, SysError>;
+
+    fn poll(&self) -> Readiness;
+
+    /// Data written at once is delivered at once, therefore writes must not be shortened.
+    fn keeps_message_boundaries(&self) -> bool {
+        false
+    }
+
+    /// Wakes up the process as soon as the readiness of the file might have changed.
+    fn register_wakeup(&self, _pid: Pid) {}
+
+    /// Called when the last descriptor referring to the file was closed.
+    fn close(&self) {}
+
+    /// Sends one message which can carry a file to the receiver.
+    fn send_message(&self, _data: &[u8], _file: Option<SharedFile>) -> Result<(), SysError> {
+        Err(SysError::InvalidArgument)
+    }
+
+    /// Files which carry files cannot be carried themselves. Otherwise they could
+    /// carry themselves and keep each other alive forever.
+    fn carries_files(&self) -> bool {
+        false
+    }
+
+    /// Receives one message. Must only be called if the file is readable.
+    /// The file the message carries is handed to accept before the message is removed,
+    /// such that the message stays queued if accept fails.
+    fn receive_message(
+        &self,
+        _buffer: &mut [u8],
+        _accept: &mut dyn FnMut(SharedFile) -> Result<(), SysError>,
+    ) -> Result<usize, SysError> {
+        Err(SysError::InvalidArgument)
+    }
+
+    /// The pages backing the file if it can be mapped into memory.
+    fn memory_pages(&self) -> Option<&[Arc<PinnedHeapPages>]> {
+        None
+    }
+}
+
+/// Drops a reference to the file and closes it if it was the last one.
+pub fn release(file: SharedFile) {
+    if Arc::strong_count(&file) == 1 {
+        file.close();
+    }
+}
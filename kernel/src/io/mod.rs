@@ -1,3 +1,7 @@
+pub mod channel;
+pub mod console;
+pub mod file;
+pub mod pipe;
 pub mod stdin_buf;
 pub mod uart;
 
@@ -0,0 +1,181 @@
+use alloc::{
+    collections::{BTreeSet, VecDeque},
+    sync::Arc,
+};
+use common::{mutex::Mutex, syscalls::SysError};
+
+use crate::processes::{process::Pid, scheduler};
+
+use super::file::{File, Readiness, SharedFile};
+
+const PIPE_CAPACITY: usize = 4096;
+
+struct PipeBuffer {
+    data: VecDeque<u8>,
+    reader_closed: bool,
+    writer_closed: bool,
+    // Readers and writers wait in the same queue since only one side can wait at a time
+    wakeup_queue: BTreeSet<Pid>,
+}
+
+impl PipeBuffer {
+    fn wake_up_waiting(&mut self) {
+        // The process table might be locked if the pipe is closed by a dying process
+        for pid in core::mem::take(&mut self.wakeup_queue) {
+            scheduler::wake_up_deferred(pid);
+        }
+    }
+}
+
+/// Returns the reading and the writing end of a new pipe.
+pub fn create() -> (SharedFile, SharedFile) {
+    let buffer = Arc::new(Mutex::new(PipeBuffer {
+        data: VecDeque::new(),
+        reader_closed: false,
+        writer_closed: false,
+        wakeup_queue: BTreeSet::new(),
+    }));
+    (
+        Arc::new(PipeReader(buffer.clone())),
+        Arc::new(PipeWriter(buffer)),
+    )
+}
+
+struct PipeReader(Arc<Mutex<PipeBuffer>>);
+
+impl File for PipeReader {
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, SysError> {
+        let mut pipe = self.0.lock();
+        let count = usize::min(pipe.data.len(), buffer.len());
+        for (byte, data) in buffer.iter_mut().zip(pipe.data.drain(..count)) {
+            *byte = data;
+        }
+        if count > 0 {
+            pipe.wake_up_waiting();
+        }
+        Ok(count)
+    }
+
+    fn write(&self, _data: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::BadDescriptor)
+    }
+
+    fn poll(&self) -> Readiness {
+        let pipe = self.0.lock();
+        Readiness {
+            // Reading returns 0 once all writers are gone
+            readable: !pipe.data.is_empty() || pipe.writer_closed,
+            writable: true,
+        }
+    }
+
+    fn register_wakeup(&self, pid: Pid) {
+        self.0.lock().wakeup_queue.insert(pid);
+    }
+
+    fn close(&self) {
+        let mut pipe = self.0.lock();
+        pipe.reader_closed = true;
+        pipe.wake_up_waiting();
+    }
+}
+
+struct PipeWriter(Arc<Mutex<PipeBuffer>>);
+
+impl File for PipeWriter {
+    fn read(&self, _buffer: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::BadDescriptor)
+    }
+
+    /// Writes as much as fits into the pipe.
+    fn write(&self, data: &[u8]) -> Result<usize, SysError> {
+        let mut pipe = self.0.lock();
+        if pipe.reader_closed {
+            return Err(SysError::BrokenPipe);
+        }
+        let count = usize::min(PIPE_CAPACITY - pipe.data.len(), data.len());
+        pipe.data.extend(&data[..count]);
+        if count > 0 {
+            pipe.wake_up_waiting();
+        }
+        Ok(count)
+    }
+
+    fn poll(&self) -> Readiness {
+        let pipe = self.0.lock();
+        Readiness {
+            readable: true,
+            // Writing fails immediately once all readers are gone
+            writable: pipe.data.len() < PIPE_CAPACITY || pipe.reader_closed,
+        }
+    }
+
+    fn register_wakeup(&self, pid: Pid) {
+        self.0.lock().wakeup_queue.insert(pid);
+    }
+
+    fn close(&self) {
+        let mut pipe = self.0.lock();
+        pipe.writer_closed = true;
+        pipe.wake_up_waiting();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use common::syscalls::SysError;
+
+    use super::{create, PIPE_CAPACITY};
+
+    #[test_case]
+    fn data_arrives_in_order() {
+        let (reader, writer) = create();
+        assert!(!reader.poll().readable);
+
+        assert_eq!(writer.write(&[1, 2, 3]), Ok(3));
+        assert!(reader.poll().readable);
+
+        let mut buffer = [0; 2];
+        assert_eq!(reader.read(&mut buffer), Ok(2));
+        assert_eq!(buffer, [1, 2]);
+        assert_eq!(reader.read(&mut buffer), Ok(1));
+        assert_eq!(buffer[0], 3);
+        assert!(!reader.poll().readable);
+    }
+
+    #[test_case]
+    fn writer_blocks_when_full() {
+        let (reader, writer) = create();
+        let data = vec![42; PIPE_CAPACITY + 1];
+
+        assert_eq!(writer.write(&data), Ok(PIPE_CAPACITY));
+        assert!(!writer.poll().writable);
+
+        let mut buffer = [0; 1];
+        assert_eq!(reader.read(&mut buffer), Ok(1));
+        assert!(writer.poll().writable);
+    }
+
+    #[test_case]
+    fn end_of_file_after_writer_closed() {
+        let (reader, writer) = create();
+        writer.write(&[1]).unwrap();
+        writer.close();
+
+        let mut buffer = [0; 4];
+        assert!(reader.poll().readable);
+        assert_eq!(reader.read(&mut buffer), Ok(1));
+        assert!(reader.poll().readable);
+        assert_eq!(reader.read(&mut buffer), Ok(0));
+    }
+
+    #[test_case]
+    fn broken_pipe_after_reader_closed() {
+        let (reader, writer) = create();
+        reader.close();
+
+        assert!(writer.poll().writable);
+        assert_eq!(writer.write(&[1]), Err(SysError::BrokenPipe));
+    }
+}
@@ -1,9 +1,6 @@
-use crate::{
-    cpu,
-    processes::{
-        process::{Pid, ProcessState},
-        scheduler, timer,
-    },
+use crate::processes::{
+    process::{Pid, ProcessState},
+    scheduler, timer,
 };
 use alloc::collections::{BTreeSet, VecDeque};
 use common::mutex::Mutex;
@@ -28,30 +25,34 @@ impl StdinBuffer {
     }
 
     pub fn push(&mut self, byte: u8) {
-        let notified = !self.wakeup_queue.is_empty();
+        self.data.push_back(byte);
+
+        if self.wakeup_queue.is_empty() {
+            return;
+        }
+
+        // The woken up processes restart their read and take the byte from the buffer
         scheduler::THE.with_lock(|s| {
             for pid in &self.wakeup_queue {
                 if let Some(process) = s.get_process(*pid) {
-                    process.with_lock(|mut p| {
-                        p.set_state(ProcessState::Runnable);
-                        p.set_syscall_return_code(byte as usize);
-                    })
+                    process.lock().set_state(ProcessState::Runnable);
                 }
             }
         });
         self.wakeup_queue.clear();
-        if notified {
-            if !cpu::is_timer_enabled() {
-                // Enable timer because we were sleeping and waiting
-                // for input
-                timer::set_timer(0);
-            }
-            return;
+
+        if !timer::is_quantum_active() {
+            // Enable timer because we were sleeping and waiting
+            // for input
+            timer::set_timer(0);
         }
-        self.data.push_back(byte);
     }
 
     pub fn pop(&mut self) -> Option<u8> {
         self.data.pop_front()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
 }
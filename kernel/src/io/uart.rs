@@ -87,6 +87,12 @@ impl Uart {
         *self.transmitter = character
     }
 
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        for byte in data {
+            self.write(*byte);
+        }
+    }
+
     fn read(&self) -> Option<u8> {
         if *self.lcr & 1 == 0 {
             return None;
@@ -97,9 +103,7 @@ impl Uart {
 
 impl Write for Uart {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        for c in s.bytes() {
-            self.write(c);
-        }
+        self.write_bytes(s.as_bytes());
         Ok(())
     }
 }
@@ -1,10 +1,11 @@
-use crate::{device_tree, info};
+use crate::{device_tree, info, klibc::util::minimum_amount_of_pages};
 
 use self::{
     page::Page,
     page_allocator::{MetadataPageAllocator, PageAllocator},
 };
-use common::mutex::Mutex;
+use alloc::vec::Vec;
+use common::{mutex::Mutex, syscalls::SysError};
 use core::{mem::MaybeUninit, ops::Range, ptr::NonNull, slice::from_raw_parts_mut};
 use linker_information::LinkerInformation;
 
@@ -14,6 +15,8 @@ pub mod page;
 mod page_allocator;
 pub mod page_tables;
 mod runtime_mappings;
+pub mod shared_memory;
+pub mod user_access;
 
 pub use page::PAGE_SIZE;
 
@@ -21,6 +24,10 @@ pub use runtime_mappings::initialize_runtime_mappings;
 
 static PAGE_ALLOCATOR: Mutex<MetadataPageAllocator> = Mutex::new(MetadataPageAllocator::new());
 
+// Pages which are kept free for the kernel (4 MiB). Memory for userspace is refused
+// before the kernel runs out of memory for page tables and its own data structures.
+const KERNEL_RESERVE_PAGES: usize = 1024;
+
 pub struct StaticPageAllocator;
 
 impl PageAllocator for StaticPageAllocator {
@@ -77,3 +84,45 @@ pub fn used_heap_pages() -> usize {
 pub fn total_heap_pages() -> usize {
     PAGE_ALLOCATOR.lock().total_heap_pages()
 }
+
+pub fn free_heap_pages() -> usize {
+    let allocator = PAGE_ALLOCATOR.lock();
+    allocator.total_heap_pages() - allocator.used_heap_pages()
+}
+
+/// True if the kernel already had to take memory from its reserve.
+#[cfg(not(miri))]
+pub fn is_starved() -> bool {
+    free_heap_pages() < KERNEL_RESERVE_PAGES
+}
+
+#[cfg(miri)]
+pub fn is_starved() -> bool {
+    false
+}
+
+/// Fails with [`SysError::OutOfMemory`] if the pages would have to be taken from the
+/// reserve of the kernel. Must be checked before allocating memory on behalf of userspace.
+#[cfg(not(miri))]
+pub fn check_user_allocation(number_of_pages: usize) -> Result<(), SysError> {
+    if free_heap_pages().saturating_sub(number_of_pages) < KERNEL_RESERVE_PAGES {
+        return Err(SysError::OutOfMemory);
+    }
+    Ok(())
+}
+
+#[cfg(miri)]
+pub fn check_user_allocation(_number_of_pages: usize) -> Result<(), SysError> {
+    Ok(())
+}
+
+/// Allocates a zeroed buffer for data of a process without panicking if memory is short.
+pub fn try_zeroed_buffer(length: usize) -> Result<Vec<u8>, SysError> {
+    check_user_allocation(minimum_amount_of_pages(length))?;
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(length)
+        .map_err(|_| SysError::OutOfMemory)?;
+    buffer.resize(length, 0);
+    Ok(buffer)
+}
@@ -1,4 +1,5 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
+use common::syscalls::SysError;
 use core::{
     num::NonZeroUsize,
     ops::{Deref, DerefMut},
@@ -60,10 +61,19 @@ pub struct PinnedHeapPages {
 }
 
 impl PinnedHeapPages {
-    pub fn new(number_of_pages: usize) -> Self {
+    /// Fails with [`SysError::OutOfMemory`] instead of taking the memory from the
+    /// reserve of the kernel.
+    pub fn new(number_of_pages: usize) -> Result<Self, SysError> {
         assert!(number_of_pages > 0);
-        let allocation = vec![Page::zero(); number_of_pages].into_boxed_slice();
-        Self { allocation }
+        super::check_user_allocation(number_of_pages)?;
+        let mut allocation = Vec::new();
+        allocation
+            .try_reserve_exact(number_of_pages)
+            .map_err(|_| SysError::OutOfMemory)?;
+        allocation.resize(number_of_pages, Page::zero());
+        Ok(Self {
+            allocation: allocation.into_boxed_slice(),
+        })
     }
 
     pub fn fill(&mut self, data: &[u8]) {
@@ -77,6 +87,10 @@ impl PinnedHeapPages {
     pub fn addr(&mut self) -> NonZeroUsize {
         self.as_mut_ptr().addr()
     }
+
+    pub fn contains_address(&self, address: usize) -> bool {
+        self.as_ptr_range().contains(&(address as *const Page))
+    }
 }
 
 impl Deref for PinnedHeapPages {
@@ -95,6 +109,8 @@ impl DerefMut for PinnedHeapPages {
 
 #[cfg(test)]
 mod tests {
+    use common::syscalls::SysError;
+
     use crate::memory::{page::Pages, PAGE_SIZE};
 
     use super::{Page, PinnedHeapPages};
@@ -107,14 +123,14 @@ mod tests {
 
     #[test_case]
     fn new() {
-        let heap_pages = PinnedHeapPages::new(2);
+        let heap_pages = PinnedHeapPages::new(2).unwrap();
         assert_eq!(heap_pages.allocation.len(), 2);
     }
 
     #[test_case]
     fn with_data() {
         let data = [1u8, 2, 3];
-        let mut heap_pages = PinnedHeapPages::new(1);
+        let mut heap_pages = PinnedHeapPages::new(1).unwrap();
         heap_pages.fill(&data);
         assert_eq!(heap_pages.len(), 1);
         let heap_slice = heap_pages.as_u8_slice();
@@ -126,7 +142,7 @@ mod tests {
     fn with_more_data() {
         const LENGTH: usize = PAGE_SIZE + 3;
         let data = [42u8; LENGTH];
-        let mut heap_pages = PinnedHeapPages::new(2);
+        let mut heap_pages = PinnedHeapPages::new(2).unwrap();
         heap_pages.fill(&data);
         assert_eq!(heap_pages.len(), 2);
         let heap_slice = heap_pages.as_u8_slice();
@@ -134,9 +150,19 @@ mod tests {
         assert_eq!(&heap_slice[LENGTH..], [0; PAGE_SIZE - 3]);
     }
 
+    #[test_case]
+    fn contains_address() {
+        let mut heap_pages = PinnedHeapPages::new(2).unwrap();
+        let start = heap_pages.addr().get();
+        assert!(heap_pages.contains_address(start));
+        assert!(heap_pages.contains_address(start + PAGE_SIZE + 42));
+        assert!(!heap_pages.contains_address(start + 2 * PAGE_SIZE));
+        assert!(!heap_pages.contains_address(start - 1));
+    }
+
     #[test_case]
     fn as_u8_slice_works() {
-        let mut heap_pages = PinnedHeapPages::new(2);
+        let mut heap_pages = PinnedHeapPages::new(2).unwrap();
         let u8_slice = heap_pages.as_u8_slice();
         assert_eq!(u8_slice.len(), PAGE_SIZE * 2);
         assert_eq!(
@@ -144,4 +170,15 @@ mod tests {
             heap_pages.allocation.as_ptr()
         );
     }
+
+    // There is no page allocator under miri
+    #[cfg(not(miri))]
+    #[test_case]
+    fn new_fails_without_memory() {
+        let number_of_pages = crate::memory::free_heap_pages();
+        assert_eq!(
+            PinnedHeapPages::new(number_of_pages).unwrap_err(),
+            SysError::OutOfMemory
+        );
+    }
 }
@@ -26,6 +26,8 @@ impl PageStatus {
 pub(super) struct MetadataPageAllocator<'a> {
     metadata: &'a mut [PageStatus],
     pages: Range<*mut MaybeUninit<Page>>,
+    // Kept up to date such that the memory pressure can be checked on every allocation
+    used_pages: usize,
 }
 
 // SAFETY: The metadata page allocator can be accessed from any thread
@@ -45,6 +47,7 @@ impl<'a> MetadataPageAllocator<'a> {
         Self {
             metadata: &mut [],
             pages: null_mut()..null_mut(),
+            used_pages: 0,
         }
     }
 
@@ -80,6 +83,7 @@ impl<'a> MetadataPageAllocator<'a> {
         };
 
         self.pages = heap.as_mut_ptr_range();
+        self.used_pages = 0;
 
         // Set reserved areas to used
         for area in reserved_areas {
@@ -97,7 +101,7 @@ impl<'a> MetadataPageAllocator<'a> {
     }
 
     pub fn used_heap_pages(&self) -> usize {
-        self.metadata.iter().filter(|m| !m.is_free()).count()
+        self.used_pages
     }
 
     fn page_idx_to_pointer(&self, page_index: usize) -> NonNull<MaybeUninit<Page>> {
@@ -162,6 +166,7 @@ impl<'a> MetadataPageAllocator<'a> {
 
             self.metadata[idx] = status;
         }
+        self.used_pages += number_of_pages;
     }
 
     fn range_to_start_aligned_and_number_of_pages<T>(
@@ -199,6 +204,7 @@ impl<'a> MetadataPageAllocator<'a> {
         }
         self.metadata[idx] = PageStatus::Free;
         count += 1;
+        self.used_pages -= count;
         count
     }
 }
@@ -263,6 +269,7 @@ mod tests {
         let _pages = alloc(number_of_pages).unwrap();
         assert!(alloc(1).is_none());
         let allocator = PAGE_ALLOC.lock();
+        assert_eq!(allocator.used_heap_pages(), number_of_pages);
         let (last, all_metadata_except_last) = allocator.metadata.split_last().unwrap();
         assert!(all_metadata_except_last
             .iter()
@@ -344,6 +351,7 @@ mod tests {
             ]
         );
         dealloc(page3);
+        assert_eq!(PAGE_ALLOC.lock().used_heap_pages(), 0);
         assert_eq!(
             PAGE_ALLOC.lock().metadata[..6],
             [
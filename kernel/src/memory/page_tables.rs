@@ -6,11 +6,11 @@ use core::{
 };
 
 use alloc::{boxed::Box, vec::Vec};
-use common::{mutex::Mutex, util::align_up};
+use common::{mutex::Mutex, syscalls::MemoryProtection, util::align_up};
 
 use crate::{
     assert::static_assert_size,
-    cpu::{read_satp, write_satp_and_fence},
+    cpu::{flush_tlb, read_satp, write_satp_and_fence},
     debug, debugging, info,
     interrupts::plic,
     io::TEST_DEVICE_ADDRESSS,
@@ -68,21 +68,34 @@ unsafe impl Sync for LazyStaticKernelPageTables {}
 
 /// Keeps track of already mapped virtual address ranges
 /// We use that to prevent of overlapping mapping
+#[derive(Clone)]
 struct MappingEntry {
     virtual_range: core::ops::Range<usize>,
     name: &'static str,
     privileges: XWRMode,
+    is_user_mode_accessible: bool,
 }
 
 impl MappingEntry {
-    fn new(virtual_range: Range<usize>, name: &'static str, privileges: XWRMode) -> Self {
+    fn new(
+        virtual_range: Range<usize>,
+        name: &'static str,
+        privileges: XWRMode,
+        is_user_mode_accessible: bool,
+    ) -> Self {
         Self {
             virtual_range,
             name,
             privileges,
+            is_user_mode_accessible,
         }
     }
 
+    fn pages(&self) -> impl Iterator<Item = usize> {
+        // The end of the virtual range points to the start of the last page
+        (self.virtual_range.start..=self.virtual_range.end).step_by(PAGE_SIZE)
+    }
+
     fn contains(&self, range: Range<usize>) -> bool {
         self.virtual_range.start <= range.end && range.start <= self.virtual_range.end
     }
@@ -296,6 +309,34 @@ impl RootPageTableHolder {
         Some(third_level_entry)
     }
 
+    fn get_page_table_entry_for_address_mut(
+        &mut self,
+        address: usize,
+    ) -> Option<&mut PageTableEntry> {
+        let root_page_table = self.table_mut();
+
+        let first_level_entry = root_page_table.get_entry_for_virtual_address_mut(address, 2);
+        if !first_level_entry.get_validity() {
+            return None;
+        }
+
+        let second_level_entry = first_level_entry
+            .get_target_page_table()
+            .get_entry_for_virtual_address_mut(address, 1);
+        if !second_level_entry.get_validity() {
+            return None;
+        }
+
+        let third_level_entry = second_level_entry
+            .get_target_page_table()
+            .get_entry_for_virtual_address_mut(address, 0);
+        if !third_level_entry.get_validity() {
+            return None;
+        }
+
+        Some(third_level_entry)
+    }
+
     fn map(
         &mut self,
         virtual_address_start: usize,
@@ -343,6 +384,7 @@ impl RootPageTableHolder {
             virtual_address_start..virtual_end,
             name,
             privileges,
+            is_user_mode_accessible,
         ));
 
         let root_page_table = self.table_mut();
@@ -352,8 +394,11 @@ impl RootPageTableHolder {
         let virtual_address_with_offset = |offset| virtual_address_start + offset;
         let physical_address_with_offset = |offset| physical_address_start + offset;
 
+        // Userspace is always mapped with 4KiB pages. This allows us to change
+        // the permissions of single pages later on (e.g. for copy-on-write).
         let can_be_mapped_with = |mapped_bytes, offset| {
-            mapped_bytes <= (size - offset)
+            !is_user_mode_accessible
+                && mapped_bytes <= (size - offset)
                 && is_aligned(virtual_address_with_offset(offset), mapped_bytes)
                 && is_aligned(physical_address_with_offset(offset), mapped_bytes)
         };
@@ -419,26 +464,8 @@ impl RootPageTableHolder {
             );
 
             // Map single page
-            let first_level_entry = root_page_table
-                .get_entry_for_virtual_address_mut(virtual_address_with_offset(offset), 2);
-            if first_level_entry.get_physical_address().is_null() {
-                let page = Box::leak(Box::new(PageTable::zero()));
-                first_level_entry.set_physical_address(&mut *page);
-                first_level_entry.set_validity(true);
-            }
-
-            let second_level_entry = first_level_entry
-                .get_target_page_table()
-                .get_entry_for_virtual_address_mut(virtual_address_with_offset(offset), 1);
-            if second_level_entry.get_physical_address().is_null() {
-                let page = Box::leak(Box::new(PageTable::zero()));
-                second_level_entry.set_physical_address(&mut *page);
-                second_level_entry.set_validity(true);
-            }
-
-            let third_level_entry = second_level_entry
-                .get_target_page_table()
-                .get_entry_for_virtual_address_mut(virtual_address_with_offset(offset), 0);
+            let third_level_entry = root_page_table
+                .get_or_create_leaf_entry_for_virtual_address(virtual_address_with_offset(offset));
 
             assert!(!third_level_entry.get_validity());
 
@@ -484,6 +511,152 @@ impl RootPageTableHolder {
             .is_some_and(|entry| entry.get_user_mode_accessible())
     }
 
+    /// Creates a new page table which shares all userspace pages with this one.
+    /// Writable pages are made read-only and marked as copy-on-write in both page tables.
+    /// The first write to such a page must be resolved with [`Self::resolve_copy_on_write`].
+    pub fn fork_userspace(&mut self) -> Self {
+        let mut forked = Self::new_with_kernel_mapping();
+
+        let userspace_mappings = self
+            .already_mapped
+            .iter()
+            .filter(|mapping| mapping.is_user_mode_accessible)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for mapping in userspace_mappings {
+            for address in mapping.pages() {
+                let entry = self
+                    .get_page_table_entry_for_address_mut(address)
+                    .expect("Userspace mapping must be present in the page table");
+                entry.mark_copy_on_write();
+                let entry = *entry;
+
+                let forked_entry = forked
+                    .table_mut()
+                    .get_or_create_leaf_entry_for_virtual_address(address);
+                assert!(!forked_entry.get_validity());
+                *forked_entry = entry;
+            }
+            forked.already_mapped.push(mapping);
+        }
+
+        self.flush_if_active();
+
+        forked
+    }
+
+    /// Returns the physical address of the page if the address is part of a copy-on-write page.
+    pub fn get_copy_on_write_page(&self, address: usize) -> Option<usize> {
+        self.get_page_table_entry_for_address(address)
+            .filter(|entry| entry.get_user_mode_accessible() && entry.is_copy_on_write())
+            .map(|entry| entry.get_physical_address() as usize)
+    }
+
+    /// Makes the copy-on-write page containing the address writable again.
+    /// If a new physical page is given, the virtual page is remapped to it.
+    pub fn resolve_copy_on_write(&mut self, address: usize, new_physical_page: Option<usize>) {
+        let entry = self
+            .get_page_table_entry_for_address_mut(address)
+            .expect("Copy-on-write page must be mapped");
+        assert!(entry.is_copy_on_write());
+
+        if let Some(new_physical_page) = new_physical_page {
+            assert!(is_aligned(new_physical_page, PAGE_SIZE));
+            entry.set_leaf_address(new_physical_page);
+        }
+        entry.clear_copy_on_write();
+
+        self.flush_if_active();
+    }
+
+    /// Removes all userspace pages in the range from the page table.
+    /// Mappings which only partially overlap the range are shrunk.
+    pub fn unmap_userspace(&mut self, virtual_address_start: usize, size: usize) {
+        assert!(is_aligned(virtual_address_start, PAGE_SIZE));
+        assert!(is_aligned(size, PAGE_SIZE));
+        if size == 0 {
+            return;
+        }
+        // The end is inclusive and points to the start of the last page
+        let last_page = virtual_address_start + size - PAGE_SIZE;
+
+        for address in (virtual_address_start..=last_page).step_by(PAGE_SIZE) {
+            if let Some(entry) = self.get_page_table_entry_for_address_mut(address) {
+                assert!(entry.get_user_mode_accessible());
+                *entry = PageTableEntry(null_mut());
+            }
+        }
+
+        let mut remaining = Vec::new();
+        for mapping in self.already_mapped.drain(..) {
+            if !mapping.is_user_mode_accessible
+                || !mapping.contains(virtual_address_start..last_page)
+            {
+                remaining.push(mapping);
+                continue;
+            }
+            if mapping.virtual_range.start < virtual_address_start {
+                let mut before = mapping.clone();
+                before.virtual_range.end = virtual_address_start - PAGE_SIZE;
+                remaining.push(before);
+            }
+            if mapping.virtual_range.end > last_page {
+                let mut after = mapping;
+                after.virtual_range.start = last_page + PAGE_SIZE;
+                remaining.push(after);
+            }
+        }
+        self.already_mapped = remaining;
+
+        self.flush_if_active();
+    }
+
+    /// Changes the privileges of all mapped userspace pages in the range.
+    /// Copy-on-write pages stay read-only until their first write is resolved.
+    pub fn change_userspace_privileges(
+        &mut self,
+        virtual_address_start: usize,
+        size: usize,
+        privileges: XWRMode,
+    ) {
+        assert!(is_aligned(virtual_address_start, PAGE_SIZE));
+        assert!(is_aligned(size, PAGE_SIZE));
+
+        for address in (virtual_address_start..virtual_address_start + size).step_by(PAGE_SIZE) {
+            if let Some(entry) = self.get_page_table_entry_for_address_mut(address) {
+                assert!(entry.get_user_mode_accessible());
+                if entry.is_copy_on_write() {
+                    entry.set_xwr_mode(privileges.without_write());
+                } else {
+                    entry.set_xwr_mode(privileges);
+                }
+            }
+        }
+
+        self.flush_if_active();
+    }
+
+    fn flush_if_active(&self) {
+        if self.is_active() {
+            flush_tlb();
+        }
+    }
+
+    pub fn is_readable_userspace_address(&self, address: usize) -> bool {
+        self.get_page_table_entry_for_address(address)
+            .is_some_and(|entry| {
+                entry.get_user_mode_accessible() && entry.get_xwr_mode().is_readable()
+            })
+    }
+
+    pub fn is_writable_userspace_address(&self, address: usize) -> bool {
+        self.get_page_table_entry_for_address(address)
+            .is_some_and(|entry| {
+                entry.get_user_mode_accessible() && entry.get_xwr_mode().is_writable()
+            })
+    }
+
     pub fn translate_userspace_address_to_physical_address<T>(
         &self,
         address: *const T,
@@ -522,6 +695,31 @@ impl PageTable {
         &mut self.0[index]
     }
 
+    fn get_or_create_leaf_entry_for_virtual_address(
+        &mut self,
+        virtual_address: usize,
+    ) -> &mut PageTableEntry {
+        let first_level_entry = self.get_entry_for_virtual_address_mut(virtual_address, 2);
+        if first_level_entry.get_physical_address().is_null() {
+            let page = Box::leak(Box::new(PageTable::zero()));
+            first_level_entry.set_physical_address(&mut *page);
+            first_level_entry.set_validity(true);
+        }
+
+        let second_level_entry = first_level_entry
+            .get_target_page_table()
+            .get_entry_for_virtual_address_mut(virtual_address, 1);
+        if second_level_entry.get_physical_address().is_null() {
+            let page = Box::leak(Box::new(PageTable::zero()));
+            second_level_entry.set_physical_address(&mut *page);
+            second_level_entry.set_validity(true);
+        }
+
+        second_level_entry
+            .get_target_page_table()
+            .get_entry_for_virtual_address_mut(virtual_address, 0)
+    }
+
     fn get_entry_for_virtual_address(&self, virtual_address: usize, level: u8) -> &PageTableEntry {
         assert!(level <= 2);
         let shifted_address = virtual_address >> (12 + 9 * level);
@@ -549,12 +747,48 @@ pub enum XWRMode {
     ReadWriteExecute = 0b111,
 }
 
+impl XWRMode {
+    fn without_write(self) -> Self {
+        match self {
+            Self::ReadWrite => Self::ReadOnly,
+            Self::ReadWriteExecute => Self::ReadExecute,
+            mode => mode,
+        }
+    }
+
+    fn with_write(self) -> Self {
+        match self {
+            Self::ReadOnly => Self::ReadWrite,
+            Self::ReadExecute => Self::ReadWriteExecute,
+            mode => mode,
+        }
+    }
+
+    pub fn is_readable(&self) -> bool {
+        matches!(
+            self,
+            Self::ReadOnly | Self::ReadWrite | Self::ReadExecute | Self::ReadWriteExecute
+        )
+    }
+
+    pub fn is_writable(&self) -> bool {
+        matches!(self, Self::ReadWrite | Self::ReadWriteExecute)
+    }
+}
+
 impl From<u8> for XWRMode {
     fn from(value: u8) -> Self {
         unsafe { core::mem::transmute(value) }
     }
 }
 
+impl From<MemoryProtection> for XWRMode {
+    fn from(value: MemoryProtection) -> Self {
+        // The bits of the protection match the XWR bits of a page table entry
+        (value.bits() as u8).into()
+    }
+}
+
 impl From<elf::ProgramHeaderFlags> for XWRMode {
     fn from(value: elf::ProgramHeaderFlags) -> Self {
         match value {
@@ -577,6 +811,8 @@ impl PageTableEntry {
     #[allow(dead_code)]
     const EXECUTE_BIT_POS: usize = 3;
     const USER_MODE_ACCESSIBLE_BIT_POS: usize = 4;
+    // Bit 8 and 9 are reserved for the supervisor software
+    const COPY_ON_WRITE_BIT_POS: usize = 8;
     const PHYSICAL_PAGE_BIT_POS: usize = 10;
     const PHYSICAL_PAGE_BITS: usize = 0xfffffffffff;
 
@@ -604,6 +840,28 @@ impl PageTableEntry {
         get_bit(self.0.addr(), PageTableEntry::USER_MODE_ACCESSIBLE_BIT_POS)
     }
 
+    fn is_copy_on_write(&self) -> bool {
+        get_bit(self.0.addr(), PageTableEntry::COPY_ON_WRITE_BIT_POS)
+    }
+
+    fn mark_copy_on_write(&mut self) {
+        let mode = self.get_xwr_mode();
+        if !mode.is_writable() {
+            return;
+        }
+        self.set_xwr_mode(mode.without_write());
+        self.0 = self.0.map_addr(|mut addr| {
+            set_or_clear_bit(&mut addr, true, PageTableEntry::COPY_ON_WRITE_BIT_POS)
+        });
+    }
+
+    fn clear_copy_on_write(&mut self) {
+        self.set_xwr_mode(self.get_xwr_mode().with_write());
+        self.0 = self.0.map_addr(|mut addr| {
+            set_or_clear_bit(&mut addr, false, PageTableEntry::COPY_ON_WRITE_BIT_POS)
+        });
+    }
+
     fn set_xwr_mode(&mut self, mode: XWRMode) {
         self.0 = self.0.map_addr(|mut addr| {
             set_multiple_bits(&mut addr, mode as u8, 3, PageTableEntry::READ_BIT_POS)
@@ -673,11 +931,88 @@ pub fn activate_page_table(page_table_holder: &RootPageTableHolder) {
 
 #[cfg(test)]
 mod tests {
-    use super::RootPageTableHolder;
+    use super::{RootPageTableHolder, XWRMode};
 
     #[test_case]
     fn check_drop_of_page_table_holder() {
         let mut page_table = RootPageTableHolder::empty();
         page_table.map_userspace(0x1000, 0x2000, 0x3000, super::XWRMode::ReadOnly, "Test");
     }
+
+    #[test_case]
+    fn fork_marks_writable_pages_copy_on_write() {
+        let mut page_table = RootPageTableHolder::empty();
+        page_table.map_userspace(0x1000, 0x2000, 0x1000, XWRMode::ReadWrite, "Data");
+        page_table.map_userspace(0x2000, 0x3000, 0x1000, XWRMode::ReadExecute, "Text");
+
+        let mut forked = page_table.fork_userspace();
+
+        for table in [&page_table, &forked] {
+            let data = table.get_page_table_entry_for_address(0x1000).unwrap();
+            assert!(data.is_copy_on_write());
+            assert_eq!(data.get_xwr_mode(), XWRMode::ReadOnly);
+            assert_eq!(data.get_physical_address() as usize, 0x2000);
+
+            let text = table.get_page_table_entry_for_address(0x2000).unwrap();
+            assert!(!text.is_copy_on_write());
+            assert_eq!(text.get_xwr_mode(), XWRMode::ReadExecute);
+            assert_eq!(text.get_physical_address() as usize, 0x3000);
+        }
+
+        assert_eq!(forked.get_copy_on_write_page(0x1234), Some(0x2000));
+        assert_eq!(forked.get_copy_on_write_page(0x2000), None);
+
+        forked.resolve_copy_on_write(0x1234, Some(0x5000));
+        let data = forked.get_page_table_entry_for_address(0x1000).unwrap();
+        assert!(!data.is_copy_on_write());
+        assert_eq!(data.get_xwr_mode(), XWRMode::ReadWrite);
+        assert_eq!(data.get_physical_address() as usize, 0x5000);
+
+        page_table.resolve_copy_on_write(0x1000, None);
+        let data = page_table.get_page_table_entry_for_address(0x1000).unwrap();
+        assert!(!data.is_copy_on_write());
+        assert_eq!(data.get_xwr_mode(), XWRMode::ReadWrite);
+        assert_eq!(data.get_physical_address() as usize, 0x2000);
+    }
+
+    #[test_case]
+    fn unmap_shrinks_overlapping_mappings() {
+        let mut page_table = RootPageTableHolder::empty();
+        page_table.map_userspace(0x1000, 0x5000, 0x4000, XWRMode::ReadWrite, "Heap");
+
+        page_table.unmap_userspace(0x2000, 0x2000);
+
+        assert!(page_table.is_userspace_address(0x1000));
+        assert!(!page_table.is_userspace_address(0x2000));
+        assert!(!page_table.is_userspace_address(0x3000));
+        assert!(page_table.is_userspace_address(0x4000));
+
+        let ranges = page_table
+            .already_mapped
+            .iter()
+            .map(|mapping| mapping.virtual_range.clone())
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(ranges, [0x1000..0x1000, 0x4000..0x4000]);
+
+        // The freed range can be mapped again
+        page_table.map_userspace(0x2000, 0x9000, 0x2000, XWRMode::ReadOnly, "Heap");
+        assert!(page_table.is_userspace_address(0x3000));
+    }
+
+    #[test_case]
+    fn change_privileges_keeps_copy_on_write_pages_read_only() {
+        let mut page_table = RootPageTableHolder::empty();
+        page_table.map_userspace(0x1000, 0x5000, 0x2000, XWRMode::ReadWrite, "Heap");
+        let _forked = page_table.fork_userspace();
+        page_table.resolve_copy_on_write(0x2000, None);
+
+        page_table.change_userspace_privileges(0x1000, 0x2000, XWRMode::ReadOnly);
+        assert!(!page_table.is_writable_userspace_address(0x1000));
+        assert!(!page_table.is_writable_userspace_address(0x2000));
+
+        page_table.change_userspace_privileges(0x1000, 0x2000, XWRMode::ReadWrite);
+        assert!(!page_table.is_writable_userspace_address(0x1000));
+        assert_eq!(page_table.get_copy_on_write_page(0x1000), Some(0x5000));
+        assert!(page_table.is_writable_userspace_address(0x2000));
+    }
 }
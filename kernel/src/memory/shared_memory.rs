@@ -0,0 +1,48 @@
+use alloc::{sync::Arc, vec::Vec};
+use common::syscalls::SysError;
+
+use crate::io::file::{File, Readiness};
+
+use super::page::PinnedHeapPages;
+
+/// Memory which several processes can map at the same time. The pages are freed
+/// once the last descriptor is closed and the last mapping is removed.
+pub struct SharedMemory {
+    // Single pages such that they can be unmapped individually
+    pages: Vec<Arc<PinnedHeapPages>>,
+}
+
+impl SharedMemory {
+    pub fn new(number_of_pages: usize) -> Result<Self, SysError> {
+        if number_of_pages == 0 {
+            return Err(SysError::InvalidArgument);
+        }
+        // Fail early instead of allocating most of the pages before running out of memory
+        super::check_user_allocation(number_of_pages)?;
+        let pages = (0..number_of_pages)
+            .map(|_| PinnedHeapPages::new(1).map(Arc::new))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { pages })
+    }
+}
+
+impl File for SharedMemory {
+    fn read(&self, _buffer: &mut [u8]) -> Result<usize, SysError> {
+        Err(SysError::InvalidArgument)
+    }
+
+    fn write(&self, _data: &[u8]) -> Result<usize, SysError> {
+        Err(SysError::InvalidArgument)
+    }
+
+    fn poll(&self) -> Readiness {
+        Readiness {
+            readable: true,
+            writable: true,
+        }
+    }
+
+    fn memory_pages(&self) -> Option<&[Arc<PinnedHeapPages>]> {
+        Some(&self.pages)
+    }
+}
@@ -0,0 +1,71 @@
+//! The kernel can only access user memory within these routines because `sstatus.SUM` is
+//! cleared otherwise. Faults while accessing the memory are caught by the fixup table in
+//! `user_access.S` and turned into errors, so that a bogus pointer cannot crash the kernel.
+//! The page table of the current process must be active.
+
+unsafe extern "C" {
+    // Returns 0 on success and 1 if the memory could not be accessed
+    fn copy_user(destination: *mut u8, source: *const u8, length: usize) -> usize;
+}
+
+fn copy(destination: *mut u8, source: *const u8, length: usize) -> Result<(), ()> {
+    if cfg!(miri) {
+        // SAFETY: There is no userspace under miri, so both pointers point to valid memory
+        unsafe { core::ptr::copy_nonoverlapping(source, destination, length) };
+        return Ok(());
+    }
+
+    // SAFETY: Faults while accessing the memory are caught by the fixup table
+    match unsafe { copy_user(destination, source, length) } {
+        0 => Ok(()),
+        _ => Err(()),
+    }
+}
+
+/// Copies memory of the current process at the address into the buffer.
+pub fn copy_from_user(buffer: &mut [u8], address: usize) -> Result<(), ()> {
+    copy(
+        buffer.as_mut_ptr(),
+        core::ptr::without_provenance(address),
+        buffer.len(),
+    )
+}
+
+/// Copies the data into the memory of the current process at the address.
+pub fn copy_to_user(address: usize, data: &[u8]) -> Result<(), ()> {
+    copy(
+        core::ptr::without_provenance_mut(address),
+        data.as_ptr(),
+        data.len(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy_from_user, copy_to_user};
+
+    static READ_ONLY: [u8; 4] = [1, 2, 3, 4];
+
+    #[test_case]
+    fn copy_accessible_memory() {
+        let mut buffer = [0; 4];
+        assert!(copy_from_user(&mut buffer, READ_ONLY.as_ptr() as usize).is_ok());
+        assert_eq!(buffer, READ_ONLY);
+
+        let mut destination = [0u8; 4];
+        assert!(copy_to_user(destination.as_mut_ptr() as usize, &buffer).is_ok());
+        assert_eq!(destination, READ_ONLY);
+    }
+
+    // Miri cannot catch faults
+    #[cfg(not(miri))]
+    #[test_case]
+    fn faults_are_reported_as_errors() {
+        let mut buffer = [0; 4];
+        // Nothing is mapped at this address
+        assert!(copy_from_user(&mut buffer, 0x1000).is_err());
+        assert!(copy_to_user(0x1000, &buffer).is_err());
+        assert!(copy_to_user(READ_ONLY.as_ptr() as usize, &buffer).is_err());
+        assert_eq!(READ_ONLY, [1, 2, 3, 4]);
+    }
+}
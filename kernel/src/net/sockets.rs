@@ -5,9 +5,16 @@ use alloc::{
     sync::{Arc, Weak},
     vec::Vec,
 };
-use common::mutex::Mutex;
+use common::{mutex::Mutex, syscalls::SysError};
 
-use crate::debug;
+use crate::{
+    debug,
+    io::file::{File, Readiness},
+    klibc::util::minimum_amount_of_pages,
+    memory,
+};
+
+use super::{udp::UdpHeader, ARP_CACHE};
 
 pub type SharedAssignedSocket = Arc<Mutex<AssignedSocket>>;
 type WeakSharedAssignedSocket = Weak<Mutex<AssignedSocket>>;
@@ -84,6 +91,16 @@ impl AssignedSocket {
     }
 
     fn put_data(&mut self, from: Ipv4Addr, from_port: u16, data: &[u8]) {
+        // Like on a full socket buffer the data is dropped if the buffer cannot grow
+        if memory::check_user_allocation(minimum_amount_of_pages(data.len())).is_err()
+            || self.buffer.try_reserve(data.len()).is_err()
+        {
+            debug!(
+                "Dropping data for port {} because memory is short",
+                self.port
+            );
+            return;
+        }
         self.received_from = Some(from);
         self.received_port = Some(from_port);
         self.buffer.extend_from_slice(data)
@@ -109,6 +126,49 @@ impl AssignedSocket {
     }
 }
 
+impl File for Mutex<AssignedSocket> {
+    fn read(&self, buffer: &mut [u8]) -> Result<usize, SysError> {
+        super::receive_and_process_packets();
+        Ok(self.lock().get_data(buffer))
+    }
+
+    /// Answers the sender of the last received packet.
+    fn write(&self, data: &[u8]) -> Result<usize, SysError> {
+        let socket = self.lock();
+        let recv_ip = socket.get_from().ok_or(SysError::NotConnected)?;
+        let recv_port = socket.get_received_port().ok_or(SysError::NotConnected)?;
+
+        // Get mac address of receiver
+        // Since we already received a packet we should have it in the cache
+        let destination_mac = *ARP_CACHE
+            .lock()
+            .get(&recv_ip)
+            .expect("There must be a receiver mac already in the arp cache.");
+        let constructed_packet = UdpHeader::create_udp_packet(
+            recv_ip,
+            recv_port,
+            destination_mac,
+            socket.get_port(),
+            data,
+        );
+        super::send_packet(constructed_packet);
+        Ok(data.len())
+    }
+
+    // Every write is sent as one datagram
+    fn keeps_message_boundaries(&self) -> bool {
+        true
+    }
+
+    // The network card does not raise interrupts, therefore reading never blocks
+    fn poll(&self) -> Readiness {
+        Readiness {
+            readable: true,
+            writable: true,
+        }
+    }
+}
+
 impl Drop for AssignedSocket {
     fn drop(&mut self) {
         let sockets = self
@@ -0,0 +1,1021 @@
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use common::syscalls::SysError;
+
+use crate::{
+    memory::{
+        self,
+        page::PinnedHeapPages,
+        page_tables::{RootPageTableHolder, XWRMode},
+        user_access::{copy_from_user, copy_to_user},
+        PAGE_SIZE,
+    },
+    processes::loader::STACK_END,
+};
+
+const FREE_MMAP_START_ADDRESS: usize = 0x2000000000;
+// End of the lower half of the Sv39 address space
+const FREE_MMAP_END_ADDRESS: usize = 0x4000000000;
+
+// Maximum size of the stack of the main thread. The stack grows on demand up to this size.
+pub const DEFAULT_STACK_LIMIT: usize = 256 * PAGE_SIZE;
+// Stack limits above this are clamped to it (64 MiB)
+const MAX_STACK_LIMIT: usize = 16384 * PAGE_SIZE;
+// A quarter of the memory of the machine (32 MiB)
+const DEFAULT_MAPPED_PAGES_LIMIT: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackFault {
+    Grown,
+    // The guard page below the stack limit was accessed
+    Overflow,
+}
+
+/// Address range reserved by mmap. Its pages are only backed by memory once they are accessed.
+#[derive(Debug, Clone)]
+struct MmapRegion {
+    end: usize,
+    privileges: XWRMode,
+    // Backed by shared memory which is mapped completely and never copied on write
+    shared: bool,
+}
+
+/// Pages allocated together which forked address spaces share copy-on-write.
+/// Sharing is counted per page such that the last address space using a page
+/// can write to it in place, even if other pages of the allocation are still shared.
+#[derive(Debug)]
+pub struct AllocatedPages {
+    pages: Arc<PinnedHeapPages>,
+    // Number of address spaces using each page. Shared by all of them.
+    users: Arc<[AtomicUsize]>,
+    // Pages of the allocation this address space uses, i.e. which were not replaced by a copy
+    used: Vec<bool>,
+}
+
+impl AllocatedPages {
+    fn new(pages: PinnedHeapPages) -> Self {
+        let users = (0..pages.len()).map(|_| AtomicUsize::new(1)).collect();
+        let used = alloc::vec![true; pages.len()];
+        Self {
+            pages: Arc::new(pages),
+            users,
+            used,
+        }
+    }
+
+    /// Number of pages this address space uses.
+    pub fn used_pages(&self) -> usize {
+        self.used.iter().filter(|&&used| used).count()
+    }
+
+    /// Whether another address space still uses any of the pages used by this one.
+    #[cfg(test)]
+    pub fn is_shared(&self) -> bool {
+        (0..self.used.len()).any(|index| self.used[index] && self.is_page_shared(index))
+    }
+
+    fn is_page_shared(&self, index: usize) -> bool {
+        // Pairs with the release in `release` such that the copy of the other
+        // address space is complete before this one writes to the page
+        self.users[index].load(Ordering::Acquire) > 1
+    }
+
+    fn used_page_index(&self, physical_address: usize) -> Option<usize> {
+        if !self.pages.contains_address(physical_address) {
+            return None;
+        }
+        let index = (physical_address - self.pages.as_ptr() as usize) / PAGE_SIZE;
+        self.used[index].then_some(index)
+    }
+
+    fn release(&mut self, index: usize) {
+        if core::mem::replace(&mut self.used[index], false) {
+            self.users[index].fetch_sub(1, Ordering::Release);
+        }
+    }
+}
+
+impl Clone for AllocatedPages {
+    fn clone(&self) -> Self {
+        for (index, _) in self.used.iter().enumerate().filter(|(_, &used)| used) {
+            self.users[index].fetch_add(1, Ordering::Relaxed);
+        }
+        Self {
+            pages: self.pages.clone(),
+            users: self.users.clone(),
+            used: self.used.clone(),
+        }
+    }
+}
+
+impl Drop for AllocatedPages {
+    fn drop(&mut self) {
+        for index in 0..self.used.len() {
+            self.release(index);
+        }
+    }
+}
+
+/// The memory of a process. It is shared between all threads of the process.
+#[derive(Debug)]
+pub struct AddressSpace {
+    page_table: RootPageTableHolder,
+    // Pages are shared between forked processes until they are written (copy-on-write)
+    allocated_pages: Vec<AllocatedPages>,
+    // Keyed by the start address of the region
+    mmap_regions: BTreeMap<usize, MmapRegion>,
+    // Backing memory of the mmap regions keyed by the virtual page address.
+    // Single pages are allocated such that they can be freed individually.
+    mmap_pages: BTreeMap<usize, Arc<PinnedHeapPages>>,
+    // Lowest mapped address of the stack
+    stack_bottom: usize,
+    stack_limit: usize,
+    // Maximum number of pages reserved by all mmap regions together
+    mapped_pages_limit: usize,
+}
+
+impl AddressSpace {
+    pub fn new(
+        page_table: RootPageTableHolder,
+        allocated_pages: Vec<PinnedHeapPages>,
+        stack_bottom: usize,
+    ) -> Self {
+        Self {
+            page_table,
+            allocated_pages: allocated_pages
+                .into_iter()
+                .map(AllocatedPages::new)
+                .collect(),
+            mmap_regions: BTreeMap::new(),
+            mmap_pages: BTreeMap::new(),
+            stack_bottom,
+            stack_limit: DEFAULT_STACK_LIMIT,
+            mapped_pages_limit: DEFAULT_MAPPED_PAGES_LIMIT,
+        }
+    }
+
+    pub fn invalid() -> Self {
+        Self {
+            page_table: RootPageTableHolder::invalid(),
+            allocated_pages: Vec::new(),
+            mmap_regions: BTreeMap::new(),
+            mmap_pages: BTreeMap::new(),
+            stack_bottom: STACK_END,
+            stack_limit: PAGE_SIZE,
+            mapped_pages_limit: 0,
+        }
+    }
+
+    /// Creates a copy of the address space which shares all pages copy-on-write.
+    /// Shared memory stays writable for both address spaces.
+    pub fn fork(&mut self) -> Self {
+        let mut forked = Self {
+            page_table: self.page_table.fork_userspace(),
+            allocated_pages: self.allocated_pages.clone(),
+            mmap_regions: self.mmap_regions.clone(),
+            mmap_pages: self.mmap_pages.clone(),
+            stack_bottom: self.stack_bottom,
+            stack_limit: self.stack_limit,
+            mapped_pages_limit: self.mapped_pages_limit,
+        };
+        self.unshare_copy_on_write_of_shared_memory();
+        forked.unshare_copy_on_write_of_shared_memory();
+        forked
+    }
+
+    fn unshare_copy_on_write_of_shared_memory(&mut self) {
+        for (&start, region) in &self.mmap_regions {
+            if !region.shared {
+                continue;
+            }
+            for page in (start..region.end).step_by(PAGE_SIZE) {
+                if self.page_table.get_copy_on_write_page(page).is_some() {
+                    self.page_table.resolve_copy_on_write(page, None);
+                }
+            }
+        }
+    }
+
+    pub fn page_table(&self) -> &RootPageTableHolder {
+        &self.page_table
+    }
+
+    pub fn allocated_pages(&self) -> &[AllocatedPages] {
+        &self.allocated_pages
+    }
+
+    /// Number of pages backed by memory. Pages shared with other processes are included.
+    pub fn resident_pages(&self) -> usize {
+        let allocated: usize = self
+            .allocated_pages
+            .iter()
+            .map(AllocatedPages::used_pages)
+            .sum();
+        allocated + self.mmap_pages.len()
+    }
+
+    /// Number of pages reserved by mmap regions. They are not necessarily backed by memory.
+    pub fn mapped_pages(&self) -> usize {
+        self.mmap_regions
+            .iter()
+            .map(|(start, region)| (region.end - start) / PAGE_SIZE)
+            .sum()
+    }
+
+    pub fn mapped_pages_limit(&self) -> usize {
+        self.mapped_pages_limit
+    }
+
+    /// Existing mappings are kept even if they exceed the new limit.
+    pub fn set_mapped_pages_limit(&mut self, number_of_pages: usize) {
+        self.mapped_pages_limit = number_of_pages;
+    }
+
+    pub fn stack_limit_pages(&self) -> usize {
+        self.stack_limit / PAGE_SIZE
+    }
+
+    /// Limits above the maximum (like UNLIMITED) are clamped to it.
+    /// A limit below the pages the stack already uses is rejected.
+    pub fn set_stack_limit_pages(&mut self, number_of_pages: usize) -> Result<(), SysError> {
+        let number_of_pages = number_of_pages.min(MAX_STACK_LIMIT / PAGE_SIZE);
+        let used_pages = (STACK_END - self.stack_bottom).div_ceil(PAGE_SIZE);
+        if number_of_pages == 0 || number_of_pages < used_pages {
+            return Err(SysError::InvalidArgument);
+        }
+        self.stack_limit = number_of_pages * PAGE_SIZE;
+        Ok(())
+    }
+
+    fn check_mapped_pages_limit(&self, number_of_pages: usize) -> Result<(), SysError> {
+        if self.mapped_pages().saturating_add(number_of_pages) > self.mapped_pages_limit {
+            return Err(SysError::OutOfMemory);
+        }
+        Ok(())
+    }
+
+    /// Reserves address space for the pages. Memory is only allocated once a page is accessed.
+    pub fn mmap_pages(&mut self, number_of_pages: usize) -> Result<*mut u8, SysError> {
+        let size = number_of_pages
+            .checked_mul(PAGE_SIZE)
+            .filter(|size| *size > 0)
+            .ok_or(SysError::InvalidArgument)?;
+        self.check_mapped_pages_limit(number_of_pages)?;
+        // Like the heuristic overcommit of Linux: only reservations which could never
+        // be backed by memory are refused
+        memory::check_user_allocation(number_of_pages)?;
+        let start = self
+            .find_free_mmap_range(size)
+            .ok_or(SysError::OutOfMemory)?;
+        self.mmap_regions.insert(
+            start,
+            MmapRegion {
+                end: start + size,
+                privileges: XWRMode::ReadWrite,
+                shared: false,
+            },
+        );
+        Ok(core::ptr::without_provenance_mut(start))
+    }
+
+    /// Maps the shared pages readable and writable. Writes are visible to
+    /// all address spaces which map the same pages.
+    pub fn mmap_shared(&mut self, pages: &[Arc<PinnedHeapPages>]) -> Result<*mut u8, SysError> {
+        let size = pages
+            .len()
+            .checked_mul(PAGE_SIZE)
+            .filter(|size| *size > 0)
+            .ok_or(SysError::InvalidArgument)?;
+        self.check_mapped_pages_limit(pages.len())?;
+        let start = self
+            .find_free_mmap_range(size)
+            .ok_or(SysError::OutOfMemory)?;
+        self.mmap_regions.insert(
+            start,
+            MmapRegion {
+                end: start + size,
+                privileges: XWRMode::ReadWrite,
+                shared: true,
+            },
+        );
+        for (index, memory) in pages.iter().enumerate() {
+            let page = start + index * PAGE_SIZE;
+            self.page_table.map_userspace(
+                page,
+                memory.as_ptr() as usize,
+                PAGE_SIZE,
+                XWRMode::ReadWrite,
+                "Shared memory",
+            );
+            self.mmap_pages.insert(page, memory.clone());
+        }
+        Ok(core::ptr::without_provenance_mut(start))
+    }
+
+    /// Releases the memory of the pages. The range can be reused by later mmaps.
+    pub fn munmap(&mut self, address: usize, number_of_pages: usize) -> Result<(), SysError> {
+        let range = page_range(address, number_of_pages)?;
+        self.split_mmap_regions(range.clone())?;
+
+        self.page_table.unmap_userspace(range.start, range.len());
+        self.mmap_regions.retain(|start, _| !range.contains(start));
+        self.mmap_pages.retain(|page, _| !range.contains(page));
+
+        Ok(())
+    }
+
+    /// Changes the privileges of the pages including the ones which are not accessed yet.
+    pub fn mprotect(
+        &mut self,
+        address: usize,
+        number_of_pages: usize,
+        privileges: XWRMode,
+    ) -> Result<(), SysError> {
+        let range = page_range(address, number_of_pages)?;
+        self.split_mmap_regions(range.clone())?;
+
+        for (_, region) in self.mmap_regions.range_mut(range.clone()) {
+            region.privileges = privileges;
+        }
+        self.page_table
+            .change_userspace_privileges(range.start, range.len(), privileges);
+
+        Ok(())
+    }
+
+    /// Maps a zeroed page if the address belongs to an mmap region and was never accessed.
+    /// Returns false if the address is not part of such a page.
+    pub fn handle_mmap_fault(&mut self, address: usize) -> Result<bool, SysError> {
+        let Some(region) = self.mmap_region(address) else {
+            return Ok(false);
+        };
+        let page = address - (address % PAGE_SIZE);
+        if self.mmap_pages.contains_key(&page) {
+            return Ok(false);
+        }
+
+        let privileges = region.privileges;
+        let mut memory = PinnedHeapPages::new(1)?;
+        self.page_table
+            .map_userspace(page, memory.addr().get(), PAGE_SIZE, privileges, "Heap");
+        self.mmap_pages.insert(page, Arc::new(memory));
+
+        Ok(true)
+    }
+
+    fn mmap_region(&self, address: usize) -> Option<&MmapRegion> {
+        self.mmap_regions
+            .range(..=address)
+            .next_back()
+            .map(|(_, region)| region)
+            .filter(|region| address < region.end)
+    }
+
+    fn find_free_mmap_range(&self, size: usize) -> Option<usize> {
+        let mut start = FREE_MMAP_START_ADDRESS;
+        for (&region_start, region) in &self.mmap_regions {
+            if region_start - start >= size {
+                return Some(start);
+            }
+            start = region.end;
+        }
+        (FREE_MMAP_END_ADDRESS - start >= size).then_some(start)
+    }
+
+    /// Splits the mmap regions such that the range starts and ends at region boundaries.
+    /// Fails if a part of the range is not reserved by mmap.
+    fn split_mmap_regions(&mut self, range: Range<usize>) -> Result<(), SysError> {
+        let mut current = range.start;
+        while current < range.end {
+            current = self
+                .mmap_region(current)
+                .ok_or(SysError::InvalidArgument)?
+                .end;
+        }
+
+        self.split_mmap_region_at(range.start);
+        self.split_mmap_region_at(range.end);
+        Ok(())
+    }
+
+    fn split_mmap_region_at(&mut self, address: usize) {
+        let Some((_, region)) = self.mmap_regions.range_mut(..address).next_back() else {
+            return;
+        };
+        if address >= region.end {
+            return;
+        }
+        let upper = MmapRegion {
+            end: region.end,
+            ..region.clone()
+        };
+        region.end = address;
+        self.mmap_regions.insert(address, upper);
+    }
+
+    fn lowest_stack_address(&self) -> usize {
+        // The initial stack might already be larger than the limit
+        (STACK_END - (self.stack_limit - PAGE_SIZE)).min(self.stack_bottom)
+    }
+
+    /// Maps the stack down to the faulting address if the address lies within the stack limit.
+    /// Returns None if the address does not belong to the stack.
+    pub fn handle_stack_fault(&mut self, address: usize) -> Result<Option<StackFault>, SysError> {
+        let lowest_stack_address = self.lowest_stack_address();
+        let guard_page = lowest_stack_address - PAGE_SIZE..lowest_stack_address;
+        if guard_page.contains(&address) {
+            return Ok(Some(StackFault::Overflow));
+        }
+        if !(lowest_stack_address..self.stack_bottom).contains(&address) {
+            return Ok(None);
+        }
+
+        let new_stack_bottom = address - (address % PAGE_SIZE);
+        let size = self.stack_bottom - new_stack_bottom;
+        let mut pages = PinnedHeapPages::new(size / PAGE_SIZE)?;
+        self.page_table.map_userspace(
+            new_stack_bottom,
+            pages.addr().get(),
+            size,
+            XWRMode::ReadWrite,
+            "Stack",
+        );
+        self.allocated_pages.push(AllocatedPages::new(pages));
+        self.stack_bottom = new_stack_bottom;
+
+        Ok(Some(StackFault::Grown))
+    }
+
+    /// Gives the process a writable page if the address belongs to a copy-on-write page.
+    /// Returns false if the address is not part of a copy-on-write page.
+    pub fn handle_copy_on_write_fault(&mut self, address: usize) -> Result<bool, SysError> {
+        let Some(physical_page) = self.page_table.get_copy_on_write_page(address) else {
+            return Ok(false);
+        };
+
+        let page = address - (address % PAGE_SIZE);
+        let is_mmap_page = match self.mmap_region(address) {
+            // The page was made read-only after it was shared
+            Some(region) if !region.privileges.is_writable() => return Ok(false),
+            Some(_) => true,
+            None => false,
+        };
+
+        if is_mmap_page {
+            let pages = self
+                .mmap_pages
+                .get(&page)
+                .expect("Mapped mmap page must be allocated");
+            if Arc::strong_count(pages) == 1 {
+                // No other process shares the page anymore, therefore we can write to it directly
+                self.page_table.resolve_copy_on_write(address, None);
+                return Ok(true);
+            }
+            let copy = self.copy_page(address, physical_page)?;
+            self.mmap_pages.insert(page, Arc::new(copy));
+            return Ok(true);
+        }
+
+        let allocation = self
+            .allocated_pages
+            .iter()
+            .position(|pages| pages.used_page_index(physical_page).is_some())
+            .expect("Copy-on-write page must be allocated by the process");
+        let pages = &self.allocated_pages[allocation];
+        let index = pages
+            .used_page_index(physical_page)
+            .expect("Page must be used by the process");
+        if !pages.is_page_shared(index) {
+            // No other process uses the page anymore, therefore we can write to it directly
+            self.page_table.resolve_copy_on_write(address, None);
+            return Ok(true);
+        }
+
+        let copy = self.copy_page(address, physical_page)?;
+        self.allocated_pages[allocation].release(index);
+        if self.allocated_pages[allocation].used_pages() == 0 {
+            self.allocated_pages.swap_remove(allocation);
+        }
+        self.allocated_pages.push(AllocatedPages::new(copy));
+
+        Ok(true)
+    }
+
+    /// Maps a copy of the copy-on-write page at the address writable.
+    fn copy_page(
+        &mut self,
+        address: usize,
+        physical_page: usize,
+    ) -> Result<PinnedHeapPages, SysError> {
+        let mut copy = PinnedHeapPages::new(1)?;
+        // SAFETY: The page is kept alive by the allocated pages of this process
+        copy.fill(unsafe { core::slice::from_raw_parts(physical_page as *const u8, PAGE_SIZE) });
+        self.page_table
+            .resolve_copy_on_write(address, Some(copy.addr().get()));
+        Ok(copy)
+    }
+
+    /// Maps all pages of mmap regions in the range which were not accessed yet
+    /// such that the kernel can access the underlying physical memory.
+    pub fn populate_range(&mut self, address: usize, length: usize) -> Result<(), SysError> {
+        for page in pages_of(address, length)? {
+            self.handle_mmap_fault(page)?;
+        }
+        Ok(())
+    }
+
+    /// Populates the range and resolves all copy-on-write pages in it such
+    /// that the kernel can write to the underlying physical memory.
+    pub fn resolve_copy_on_write_range(
+        &mut self,
+        address: usize,
+        length: usize,
+    ) -> Result<(), SysError> {
+        self.populate_range(address, length)?;
+        for page in pages_of(address, length)? {
+            self.handle_copy_on_write_fault(page)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the process can read the whole range.
+    /// Pages of mmap regions which were not accessed yet are mapped.
+    pub fn check_readable(&mut self, address: usize, length: usize) -> Result<(), SysError> {
+        self.check_range(address, length, false)?;
+        self.populate_range(address, length)
+    }
+
+    /// Checks that the process can write the whole range. Afterwards the kernel can write
+    /// to the underlying physical memory: copy-on-write pages are resolved, pages of mmap
+    /// regions are mapped and the stack is grown if necessary.
+    pub fn check_writable(&mut self, address: usize, length: usize) -> Result<(), SysError> {
+        self.check_range(address, length, true)?;
+        self.resolve_copy_on_write_range(address, length)?;
+        // Only the first page can be below the stack, otherwise the range contains the guard page
+        if length > 0 {
+            self.handle_stack_fault(address - (address % PAGE_SIZE))?;
+        }
+        Ok(())
+    }
+
+    /// Fails at the first page of the range which the process cannot access.
+    /// Nothing is allocated, such that huge ranges are rejected cheaply.
+    fn check_range(&self, address: usize, length: usize, write: bool) -> Result<(), SysError> {
+        let end = address.checked_add(length).ok_or(SysError::BadAddress)?;
+        let mut page = address - (address % PAGE_SIZE);
+        while page < end {
+            if let Some(region) = self.mmap_region(page) {
+                let accessible = if write {
+                    region.privileges.is_writable()
+                } else {
+                    region.privileges.is_readable()
+                };
+                if !accessible {
+                    return Err(SysError::BadAddress);
+                }
+                // Pages of the region which were not accessed yet are not in the page table
+                page = region.end;
+                continue;
+            }
+            let accessible = if write {
+                self.page_table.is_writable_userspace_address(page)
+                    || self.page_table.get_copy_on_write_page(page).is_some()
+                    || (self.lowest_stack_address()..self.stack_bottom).contains(&page)
+            } else {
+                self.page_table.is_readable_userspace_address(page)
+            };
+            if !accessible {
+                return Err(SysError::BadAddress);
+            }
+            page += PAGE_SIZE;
+        }
+        Ok(())
+    }
+
+    /// Copies the data into the memory of the process. The page table of the process must be
+    /// active. Fails if the memory is not mapped writable for the process.
+    pub fn write_userspace(&mut self, address: usize, data: &[u8]) -> Result<(), ()> {
+        self.check_writable(address, data.len()).map_err(|_| ())?;
+        copy_to_user(address, data)
+    }
+
+    /// Copies memory of the process into the buffer. The page table of the process must be
+    /// active. Fails if the memory is not mapped readable for the process.
+    pub fn read_userspace(&mut self, address: usize, buffer: &mut [u8]) -> Result<(), ()> {
+        self.check_readable(address, buffer.len()).map_err(|_| ())?;
+        copy_from_user(buffer, address)
+    }
+
+    /// Like `write_userspace`, but through the physical memory because tests cannot
+    /// activate the page table of the process.
+    #[cfg(test)]
+    pub fn write_physical(&mut self, address: usize, data: &[u8]) -> Result<(), ()> {
+        self.check_writable(address, data.len()).map_err(|_| ())?;
+        self.for_each_physical_chunk(address, data.len(), |offset, physical_address, length| {
+            // SAFETY: The page is mapped writable and owned by this process
+            unsafe {
+                core::ptr::copy_nonoverlapping(data[offset..].as_ptr(), physical_address, length);
+            }
+        });
+        Ok(())
+    }
+
+    /// Like `read_userspace`, but through the physical memory because tests cannot
+    /// activate the page table of the process.
+    #[cfg(test)]
+    pub fn read_physical(&mut self, address: usize, buffer: &mut [u8]) -> Result<(), ()> {
+        self.check_readable(address, buffer.len()).map_err(|_| ())?;
+        self.for_each_physical_chunk(address, buffer.len(), |offset, physical_address, length| {
+            // SAFETY: The page is mapped readable and owned by this process
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    physical_address,
+                    buffer[offset..].as_mut_ptr(),
+                    length,
+                );
+            }
+        });
+        Ok(())
+    }
+
+    // Virtually contiguous memory is not necessarily physically contiguous,
+    // therefore the range is split at page boundaries.
+    #[cfg(test)]
+    fn for_each_physical_chunk(
+        &self,
+        address: usize,
+        length: usize,
+        mut f: impl FnMut(usize, *mut u8, usize),
+    ) {
+        let mut offset = 0;
+        while offset < length {
+            let current = address + offset;
+            let physical_address = self
+                .page_table
+                .translate_userspace_address_to_physical_address(current as *const u8)
+                .expect("Range must be checked before");
+            let chunk_length = (PAGE_SIZE - current % PAGE_SIZE).min(length - offset);
+            f(offset, physical_address as *mut u8, chunk_length);
+            offset += chunk_length;
+        }
+    }
+}
+
+fn pages_of(address: usize, length: usize) -> Result<impl Iterator<Item = usize>, SysError> {
+    let end = address.checked_add(length).ok_or(SysError::BadAddress)?;
+    let start = address - (address % PAGE_SIZE);
+    Ok((start..end).step_by(PAGE_SIZE))
+}
+
+fn page_range(address: usize, number_of_pages: usize) -> Result<Range<usize>, SysError> {
+    if address % PAGE_SIZE != 0 || number_of_pages == 0 {
+        return Err(SysError::InvalidArgument);
+    }
+    let end = number_of_pages
+        .checked_mul(PAGE_SIZE)
+        .and_then(|size| address.checked_add(size))
+        .ok_or(SysError::InvalidArgument)?;
+    Ok(address..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{sync::Arc, vec::Vec};
+    use common::syscalls::{SysError, UNLIMITED};
+
+    use crate::{
+        io::file::File,
+        memory::{
+            page_tables::{RootPageTableHolder, XWRMode},
+            shared_memory::SharedMemory,
+            PAGE_SIZE,
+        },
+        processes::loader::STACK_END,
+    };
+
+    use super::{
+        AddressSpace, StackFault, DEFAULT_STACK_LIMIT, FREE_MMAP_START_ADDRESS, MAX_STACK_LIMIT,
+    };
+
+    fn empty_address_space() -> AddressSpace {
+        AddressSpace::new(
+            RootPageTableHolder::new_with_kernel_mapping(),
+            Vec::new(),
+            STACK_END,
+        )
+    }
+
+    #[test_case]
+    fn grow_stack() {
+        let mut address_space = empty_address_space();
+        let lowest_stack_address = STACK_END - (DEFAULT_STACK_LIMIT - PAGE_SIZE);
+
+        assert_eq!(address_space.handle_stack_fault(0x1000), Ok(None));
+        assert_eq!(
+            address_space.handle_stack_fault(STACK_END - 2 * PAGE_SIZE + 8),
+            Ok(Some(StackFault::Grown))
+        );
+        assert_eq!(address_space.stack_bottom, STACK_END - 2 * PAGE_SIZE);
+        assert!(address_space
+            .page_table()
+            .is_writable_userspace_address(STACK_END - PAGE_SIZE));
+
+        assert_eq!(
+            address_space.handle_stack_fault(lowest_stack_address),
+            Ok(Some(StackFault::Grown))
+        );
+        assert_eq!(
+            address_space.handle_stack_fault(lowest_stack_address - 1),
+            Ok(Some(StackFault::Overflow))
+        );
+        assert_eq!(
+            address_space.handle_stack_fault(lowest_stack_address - PAGE_SIZE - 1),
+            Ok(None)
+        );
+    }
+
+    #[test_case]
+    fn mmap_pages() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(1).unwrap();
+        assert_eq!(ptr as usize, FREE_MMAP_START_ADDRESS);
+        let ptr = address_space.mmap_pages(2).unwrap();
+        assert_eq!(ptr as usize, FREE_MMAP_START_ADDRESS + PAGE_SIZE);
+        assert_eq!(address_space.mmap_pages(0), Err(SysError::InvalidArgument));
+        assert_eq!(
+            address_space.mmap_pages(usize::MAX / PAGE_SIZE),
+            Err(SysError::OutOfMemory)
+        );
+    }
+
+    #[test_case]
+    fn limits_restrict_mapped_pages_and_stack() {
+        let mut address_space = empty_address_space();
+        address_space.set_mapped_pages_limit(3);
+
+        let ptr = address_space.mmap_pages(2).unwrap() as usize;
+        assert_eq!(address_space.mmap_pages(2), Err(SysError::OutOfMemory));
+        address_space.munmap(ptr, 1).unwrap();
+        assert!(address_space.mmap_pages(2).is_ok());
+        assert_eq!(address_space.mapped_pages(), 3);
+
+        assert_eq!(
+            address_space.set_stack_limit_pages(0),
+            Err(SysError::InvalidArgument)
+        );
+        assert_eq!(address_space.set_stack_limit_pages(UNLIMITED), Ok(()));
+        assert_eq!(
+            address_space.stack_limit_pages(),
+            MAX_STACK_LIMIT / PAGE_SIZE
+        );
+        assert_eq!(address_space.set_stack_limit_pages(2), Ok(()));
+        assert_eq!(address_space.stack_limit_pages(), 2);
+        assert_eq!(
+            address_space.handle_stack_fault(STACK_END - 2 * PAGE_SIZE),
+            Ok(Some(StackFault::Overflow))
+        );
+    }
+
+    #[test_case]
+    fn stack_limit_cannot_go_below_used_pages() {
+        let mut address_space = empty_address_space();
+        assert_eq!(
+            address_space.handle_stack_fault(STACK_END - 3 * PAGE_SIZE),
+            Ok(Some(StackFault::Grown))
+        );
+        assert_eq!(
+            address_space.set_stack_limit_pages(2),
+            Err(SysError::InvalidArgument)
+        );
+        assert_eq!(address_space.set_stack_limit_pages(3), Ok(()));
+        assert_eq!(address_space.stack_limit_pages(), 3);
+    }
+
+    #[test_case]
+    fn mmap_pages_are_mapped_on_access() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(2).unwrap() as usize;
+        assert!(!address_space.page_table().is_userspace_address(ptr));
+
+        assert!(address_space
+            .handle_mmap_fault(ptr + PAGE_SIZE + 8)
+            .unwrap());
+        assert!(!address_space.page_table().is_userspace_address(ptr));
+        assert!(address_space
+            .page_table()
+            .is_writable_userspace_address(ptr + PAGE_SIZE));
+
+        // Already mapped pages and addresses outside of regions are no mmap faults
+        assert!(!address_space.handle_mmap_fault(ptr + PAGE_SIZE).unwrap());
+        assert!(!address_space
+            .handle_mmap_fault(ptr + 2 * PAGE_SIZE)
+            .unwrap());
+
+        let mut buffer = [1; 4];
+        assert!(address_space.read_physical(ptr, &mut buffer).is_ok());
+        assert_eq!(buffer, [0; 4]);
+    }
+
+    #[test_case]
+    fn munmap_frees_range_for_reuse() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(3).unwrap() as usize;
+        assert!(address_space
+            .write_physical(ptr, &[1; 3 * PAGE_SIZE])
+            .is_ok());
+
+        assert!(address_space.munmap(ptr + PAGE_SIZE, 1).is_ok());
+        assert!(address_space.page_table().is_userspace_address(ptr));
+        assert!(!address_space
+            .page_table()
+            .is_userspace_address(ptr + PAGE_SIZE));
+        assert!(!address_space.handle_mmap_fault(ptr + PAGE_SIZE).unwrap());
+        assert_eq!(address_space.mmap_pages.len(), 2);
+
+        // Only ranges reserved by mmap can be unmapped
+        assert!(address_space.munmap(ptr, 2).is_err());
+        assert!(address_space.munmap(ptr + 1, 1).is_err());
+        assert!(address_space.munmap(STACK_END - PAGE_SIZE, 1).is_err());
+
+        assert_eq!(
+            address_space.mmap_pages(1).unwrap() as usize,
+            ptr + PAGE_SIZE
+        );
+        assert_eq!(
+            address_space.mmap_pages(1).unwrap() as usize,
+            ptr + 3 * PAGE_SIZE
+        );
+    }
+
+    #[test_case]
+    fn mprotect_changes_mapped_and_unmapped_pages() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(2).unwrap() as usize;
+        assert!(address_space.handle_mmap_fault(ptr).unwrap());
+
+        assert!(address_space.mprotect(ptr, 2, XWRMode::ReadOnly).is_ok());
+        assert!(!address_space
+            .page_table()
+            .is_writable_userspace_address(ptr));
+        assert!(address_space.write_physical(ptr, &[1]).is_err());
+
+        assert!(address_space.handle_mmap_fault(ptr + PAGE_SIZE).unwrap());
+        assert!(address_space
+            .page_table()
+            .is_userspace_address(ptr + PAGE_SIZE));
+        assert!(!address_space
+            .page_table()
+            .is_writable_userspace_address(ptr + PAGE_SIZE));
+
+        assert!(address_space.mprotect(ptr, 1, XWRMode::ReadWrite).is_ok());
+        assert!(address_space.write_physical(ptr, &[1]).is_ok());
+        assert!(address_space.mprotect(ptr, 3, XWRMode::ReadWrite).is_err());
+    }
+
+    #[test_case]
+    fn check_permissions_of_every_page() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(3).unwrap() as usize;
+        assert!(address_space
+            .mprotect(ptr + PAGE_SIZE, 1, XWRMode::ExecuteOnly)
+            .is_ok());
+
+        // The first and last byte are accessible, the page in between is not
+        assert!(address_space.check_readable(ptr, 3 * PAGE_SIZE).is_err());
+        assert!(address_space.check_writable(ptr, 3 * PAGE_SIZE).is_err());
+        assert!(address_space.check_readable(ptr + 8, PAGE_SIZE - 8).is_ok());
+
+        assert!(address_space
+            .mprotect(ptr + PAGE_SIZE, 1, XWRMode::ReadOnly)
+            .is_ok());
+        assert!(address_space.check_readable(ptr, 3 * PAGE_SIZE).is_ok());
+        assert!(address_space.check_writable(ptr, 3 * PAGE_SIZE).is_err());
+
+        assert!(address_space.check_readable(ptr, 4 * PAGE_SIZE).is_err());
+        assert!(address_space.check_readable(usize::MAX, 2).is_err());
+        assert!(address_space.check_writable(ptr, 0).is_ok());
+    }
+
+    #[test_case]
+    fn huge_ranges_are_rejected_without_allocating() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(16).unwrap() as usize;
+        let resident_pages = address_space.resident_pages();
+
+        assert_eq!(
+            address_space.check_readable(ptr, usize::MAX / 2),
+            Err(SysError::BadAddress)
+        );
+        assert_eq!(
+            address_space.check_writable(ptr, usize::MAX / 2),
+            Err(SysError::BadAddress)
+        );
+        assert_eq!(address_space.resident_pages(), resident_pages);
+    }
+
+    #[test_case]
+    fn fork_shares_mmap_pages_copy_on_write() {
+        let mut address_space = empty_address_space();
+        let ptr = address_space.mmap_pages(1).unwrap() as usize;
+        assert!(address_space.write_physical(ptr, &[1]).is_ok());
+
+        let mut forked = address_space.fork();
+        assert!(forked.write_physical(ptr, &[2]).is_ok());
+
+        let mut buffer = [0];
+        assert!(address_space.read_physical(ptr, &mut buffer).is_ok());
+        assert_eq!(buffer, [1]);
+        assert!(forked.read_physical(ptr, &mut buffer).is_ok());
+        assert_eq!(buffer, [2]);
+
+        // Dropping the page in one address space keeps it alive in the other
+        assert!(forked.munmap(ptr, 1).is_ok());
+        assert!(address_space.read_physical(ptr, &mut buffer).is_ok());
+        assert_eq!(buffer, [1]);
+    }
+
+    #[test_case]
+    fn copy_on_write_is_tracked_per_page() {
+        let mut address_space = empty_address_space();
+        let first = STACK_END - 2 * PAGE_SIZE;
+        let second = STACK_END - PAGE_SIZE;
+        assert_eq!(
+            address_space.handle_stack_fault(first),
+            Ok(Some(StackFault::Grown))
+        );
+        assert_eq!(address_space.allocated_pages().len(), 1);
+
+        let mut forked = address_space.fork();
+        assert!(forked.write_physical(first, &[1]).is_ok());
+        assert_eq!(forked.allocated_pages().len(), 2);
+
+        // The forked address space copied the first page, therefore the
+        // original one is its only user even though the second page is still shared
+        assert!(address_space.write_physical(first, &[2]).is_ok());
+        assert_eq!(address_space.allocated_pages().len(), 1);
+        assert!(address_space
+            .page_table()
+            .get_copy_on_write_page(second)
+            .is_some());
+
+        assert!(address_space.write_physical(second, &[3]).is_ok());
+        assert_eq!(address_space.allocated_pages().len(), 2);
+        assert!(forked.write_physical(second, &[4]).is_ok());
+        assert_eq!(forked.allocated_pages().len(), 2);
+        assert!(!forked
+            .allocated_pages()
+            .iter()
+            .any(|pages| pages.is_shared()));
+
+        let mut buffer = [0];
+        for (address, value) in [(first, 2), (second, 3)] {
+            assert!(address_space.read_physical(address, &mut buffer).is_ok());
+            assert_eq!(buffer, [value]);
+        }
+        for (address, value) in [(first, 1), (second, 4)] {
+            assert!(forked.read_physical(address, &mut buffer).is_ok());
+            assert_eq!(buffer, [value]);
+        }
+    }
+
+    #[test_case]
+    fn shared_memory_is_visible_in_all_address_spaces() {
+        let memory = SharedMemory::new(2).unwrap();
+        let pages = memory.memory_pages().unwrap();
+        let mut first = empty_address_space();
+        let mut second = empty_address_space();
+        let first_ptr = first.mmap_shared(pages).unwrap() as usize;
+        let second_ptr = second.mmap_shared(&pages[1..]).unwrap() as usize;
+
+        assert!(first.write_physical(first_ptr + PAGE_SIZE, &[42]).is_ok());
+        let mut buffer = [0];
+        assert!(second.read_physical(second_ptr, &mut buffer).is_ok());
+        assert_eq!(buffer, [42]);
+
+        // Unmapping drops the reference to the page
+        assert_eq!(Arc::strong_count(&pages[1]), 3);
+        assert!(second.munmap(second_ptr, 1).is_ok());
+        assert_eq!(Arc::strong_count(&pages[1]), 2);
+    }
+
+    #[test_case]
+    fn fork_keeps_shared_memory_shared() {
+        let memory = SharedMemory::new(1).unwrap();
+        let mut address_space = empty_address_space();
+        let ptr = address_space
+            .mmap_shared(memory.memory_pages().unwrap())
+            .unwrap() as usize;
+
+        let mut forked = address_space.fork();
+        assert!(forked.page_table().is_writable_userspace_address(ptr));
+        assert!(forked.write_physical(ptr, &[7]).is_ok());
+
+        let mut buffer = [0];
+        assert!(address_space.read_physical(ptr, &mut buffer).is_ok());
+        assert_eq!(buffer, [7]);
+    }
+}
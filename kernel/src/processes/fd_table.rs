@@ -0,0 +1,258 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use common::syscalls::{SysError, STDERR, STDIN, STDOUT};
+
+use crate::io::{
+    console::Console,
+    file::{release, SharedFile},
+};
+
+// Same default limit as Linux
+const DEFAULT_DESCRIPTOR_LIMIT: usize = 1024;
+
+/// Maps the descriptors of a process to the files they refer to.
+/// Several descriptors (also of different processes) can refer to the same file.
+#[derive(Clone)]
+pub struct FileDescriptorTable {
+    files: BTreeMap<usize, SharedFile>,
+    // Not inherited by programs the process starts
+    close_on_execute: BTreeSet<usize>,
+    // All descriptors are lower than the limit
+    limit: usize,
+}
+
+impl Default for FileDescriptorTable {
+    fn default() -> Self {
+        Self {
+            files: BTreeMap::new(),
+            close_on_execute: BTreeSet::new(),
+            limit: DEFAULT_DESCRIPTOR_LIMIT,
+        }
+    }
+}
+
+impl FileDescriptorTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Already open descriptors stay open even if they exceed the new limit.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Standard input, output and error refer to the console.
+    pub fn with_console() -> Self {
+        let console: SharedFile = Arc::new(Console);
+        let mut table = Self::new();
+        for descriptor in [STDIN, STDOUT, STDERR] {
+            table.files.insert(descriptor, console.clone());
+        }
+        table
+    }
+
+    pub fn get(&self, descriptor: usize) -> Result<SharedFile, SysError> {
+        self.files
+            .get(&descriptor)
+            .cloned()
+            .ok_or(SysError::BadDescriptor)
+    }
+
+    /// Returns the lowest free descriptor which now refers to the file.
+    pub fn open(&mut self, file: SharedFile) -> Result<usize, SysError> {
+        let descriptor = (0..self.limit)
+            .find(|descriptor| !self.files.contains_key(descriptor))
+            .ok_or(SysError::TooManyFiles)?;
+        self.files.insert(descriptor, file);
+        Ok(descriptor)
+    }
+
+    /// Like open, but for a file which is already open. The new descriptor is not
+    /// inherited by started programs if close_on_execute is set.
+    pub fn dup(&mut self, descriptor: usize, close_on_execute: bool) -> Result<usize, SysError> {
+        let descriptor = self.open(self.get(descriptor)?)?;
+        if close_on_execute {
+            self.close_on_execute.insert(descriptor);
+        }
+        Ok(descriptor)
+    }
+
+    /// The descriptors of a started program. Descriptors marked close-on-execute are left out.
+    pub fn for_new_program(&self) -> Self {
+        Self {
+            files: self
+                .files
+                .iter()
+                .filter(|(descriptor, _)| !self.close_on_execute.contains(descriptor))
+                .map(|(descriptor, file)| (*descriptor, file.clone()))
+                .collect(),
+            close_on_execute: BTreeSet::new(),
+            limit: self.limit,
+        }
+    }
+
+    pub fn close(&mut self, descriptor: usize) -> Result<(), SysError> {
+        let file = self
+            .files
+            .remove(&descriptor)
+            .ok_or(SysError::BadDescriptor)?;
+        self.close_on_execute.remove(&descriptor);
+        release(file);
+        Ok(())
+    }
+
+    pub fn dup2(
+        &mut self,
+        old_descriptor: usize,
+        new_descriptor: usize,
+    ) -> Result<usize, SysError> {
+        let file = self.get(old_descriptor)?;
+        if new_descriptor >= self.limit {
+            return Err(SysError::BadDescriptor);
+        }
+        // Like a newly opened descriptor the copy is inherited
+        self.close_on_execute.remove(&new_descriptor);
+        if let Some(replaced) = self.files.insert(new_descriptor, file) {
+            release(replaced);
+        }
+        Ok(new_descriptor)
+    }
+}
+
+impl Drop for FileDescriptorTable {
+    fn drop(&mut self) {
+        for (_, file) in core::mem::take(&mut self.files) {
+            release(file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use common::syscalls::SysError;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::io::file::{File, Readiness, SharedFile};
+
+    use super::{FileDescriptorTable, DEFAULT_DESCRIPTOR_LIMIT};
+
+    #[derive(Default)]
+    struct TestFile {
+        closed: Arc<AtomicBool>,
+    }
+
+    impl File for TestFile {
+        fn read(&self, _buffer: &mut [u8]) -> Result<usize, SysError> {
+            Ok(0)
+        }
+
+        fn write(&self, data: &[u8]) -> Result<usize, SysError> {
+            Ok(data.len())
+        }
+
+        fn poll(&self) -> Readiness {
+            Readiness {
+                readable: true,
+                writable: true,
+            }
+        }
+
+        fn close(&self) {
+            self.closed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[test_case]
+    fn open_uses_lowest_free_descriptor() {
+        let mut table = FileDescriptorTable::with_console();
+        let file: SharedFile = Arc::new(TestFile::default());
+
+        assert_eq!(table.open(file.clone()), Ok(3));
+        assert_eq!(table.open(file.clone()), Ok(4));
+        assert_eq!(table.close(3), Ok(()));
+        assert_eq!(table.open(file), Ok(3));
+        assert_eq!(table.close(3), Ok(()));
+        assert_eq!(table.close(3), Err(SysError::BadDescriptor));
+    }
+
+    #[test_case]
+    fn dup2_replaces_descriptor() {
+        let mut table = FileDescriptorTable::with_console();
+        let file: SharedFile = Arc::new(TestFile::default());
+        let descriptor = table.open(file.clone()).unwrap();
+
+        assert_eq!(table.dup2(descriptor, 1), Ok(1));
+        assert!(Arc::ptr_eq(&table.get(1).unwrap(), &file));
+        assert_eq!(table.dup2(descriptor, descriptor), Ok(descriptor));
+        assert_eq!(table.dup2(42, 1), Err(SysError::BadDescriptor));
+        assert_eq!(
+            table.dup2(descriptor, DEFAULT_DESCRIPTOR_LIMIT),
+            Err(SysError::BadDescriptor)
+        );
+    }
+
+    #[test_case]
+    fn close_on_execute_descriptors_are_not_inherited() {
+        let mut table = FileDescriptorTable::with_console();
+        let saved = table.dup(1, true).unwrap();
+        assert_eq!(saved, 3);
+        assert_eq!(table.dup(1, false), Ok(4));
+        assert!(Arc::ptr_eq(
+            &table.get(saved).unwrap(),
+            &table.get(1).unwrap()
+        ));
+
+        let started = table.for_new_program();
+        assert!(started.get(saved).is_err());
+        assert!(started.get(4).is_ok());
+        // Fork keeps the flag
+        assert!(table.clone().for_new_program().get(saved).is_err());
+
+        // A descriptor replaced by dup2 is inherited again
+        assert_eq!(table.dup2(1, saved), Ok(saved));
+        assert!(table.for_new_program().get(saved).is_ok());
+        assert_eq!(table.dup(42, true), Err(SysError::BadDescriptor));
+    }
+
+    #[test_case]
+    fn file_is_closed_with_last_descriptor() {
+        let closed = Arc::new(AtomicBool::new(false));
+        let file = Arc::new(TestFile {
+            closed: closed.clone(),
+        });
+        let mut table = FileDescriptorTable::new();
+        let descriptor = table.open(file).unwrap();
+        let copy = table.dup2(descriptor, 7).unwrap();
+        let forked = table.clone();
+
+        table.close(descriptor).unwrap();
+        table.close(copy).unwrap();
+        assert!(!closed.load(Ordering::Relaxed));
+
+        drop(forked);
+        assert!(closed.load(Ordering::Relaxed));
+    }
+
+    #[test_case]
+    fn limit_restricts_new_descriptors() {
+        let mut table = FileDescriptorTable::with_console();
+        let file: SharedFile = Arc::new(TestFile::default());
+        table.set_limit(4);
+
+        assert_eq!(table.open(file.clone()), Ok(3));
+        assert_eq!(table.open(file.clone()), Err(SysError::TooManyFiles));
+        assert_eq!(table.dup2(3, 4), Err(SysError::BadDescriptor));
+
+        // Open descriptors are kept when the limit shrinks
+        table.set_limit(2);
+        assert!(table.get(3).is_ok());
+        assert_eq!(table.open(file), Err(SysError::TooManyFiles));
+    }
+}
@@ -0,0 +1,63 @@
+use alloc::collections::{BTreeMap, VecDeque};
+
+use super::process::Pid;
+
+/// Threads waiting for a change of a word in userspace memory. The words are
+/// identified by their physical address, therefore processes sharing memory
+/// can use the same futex.
+pub struct FutexTable {
+    waiters: BTreeMap<usize, VecDeque<Pid>>,
+}
+
+impl FutexTable {
+    pub const fn new() -> Self {
+        Self {
+            waiters: BTreeMap::new(),
+        }
+    }
+
+    pub fn enqueue(&mut self, physical_address: usize, pid: Pid) {
+        self.waiters
+            .entry(physical_address)
+            .or_default()
+            .push_back(pid);
+    }
+
+    pub fn is_waiting(&self, physical_address: usize, pid: Pid) -> bool {
+        self.waiters
+            .get(&physical_address)
+            .is_some_and(|waiters| waiters.contains(&pid))
+    }
+
+    /// Removes the longest waiting thread of the futex.
+    pub fn dequeue(&mut self, physical_address: usize) -> Option<Pid> {
+        let waiters = self.waiters.get_mut(&physical_address)?;
+        let pid = waiters.pop_front();
+        if waiters.is_empty() {
+            self.waiters.remove(&physical_address);
+        }
+        pid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FutexTable;
+
+    #[test_case]
+    fn dequeue_in_fifo_order() {
+        let mut futexes = FutexTable::new();
+        futexes.enqueue(0x1000, 1);
+        futexes.enqueue(0x1000, 2);
+        futexes.enqueue(0x2000, 3);
+
+        assert!(futexes.is_waiting(0x1000, 1));
+        assert!(!futexes.is_waiting(0x2000, 1));
+
+        assert_eq!(futexes.dequeue(0x1000), Some(1));
+        assert!(!futexes.is_waiting(0x1000, 1));
+        assert_eq!(futexes.dequeue(0x1000), Some(2));
+        assert_eq!(futexes.dequeue(0x1000), None);
+        assert_eq!(futexes.dequeue(0x2000), Some(3));
+    }
+}
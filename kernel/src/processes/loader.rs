@@ -1,4 +1,5 @@
 use alloc::vec::Vec;
+use common::{syscalls::SysError, util::align_up};
 
 use crate::{
     klibc::{
@@ -9,30 +10,91 @@ use crate::{
 };
 
 pub const STACK_END: usize = 0xfffffffffffff000;
-pub const STACK_START: usize = STACK_END + (PAGE_SIZE - 1);
+
+// Auxiliary vector entries as defined by the System V ABI
+const AT_NULL: usize = 0;
+const AT_PAGESZ: usize = 6;
+const AT_ENTRY: usize = 9;
 
 #[derive(Debug)]
 pub struct LoadedElf {
     pub entry_address: usize,
+    pub stack_pointer: usize,
+    pub stack_bottom: usize,
     pub page_tables: RootPageTableHolder,
     pub allocated_pages: Vec<PinnedHeapPages>,
 }
 
-pub fn load_elf(elf_file: &ElfFile) -> LoadedElf {
+fn initial_stack_size(args: &[&str], env: &[&str]) -> usize {
+    // argc, argv, NULL, envp, NULL and three auxiliary vector entries
+    let words = 1 + args.len() + 1 + env.len() + 1 + 3 * 2;
+    let strings: usize = args.iter().chain(env).map(|s| s.len() + 1).sum();
+    align_up(words * core::mem::size_of::<usize>() + strings, 16)
+}
+
+/// Builds the initial stack content as defined by the System V ABI. From the lowest
+/// address on: argc, argv pointers, NULL, envp pointers, NULL, the auxiliary vector
+/// and the null terminated strings. The content is placed at `stack_pointer`.
+fn build_initial_stack(
+    args: &[&str],
+    env: &[&str],
+    entry_address: usize,
+    stack_pointer: usize,
+) -> Vec<u8> {
+    let size = initial_stack_size(args, env);
+    let strings_address = stack_pointer
+        + (1 + args.len() + 1 + env.len() + 1 + 3 * 2) * core::mem::size_of::<usize>();
+
+    let mut strings = Vec::new();
+    let mut push_string = |s: &str| {
+        let address = strings_address + strings.len();
+        strings.extend_from_slice(s.as_bytes());
+        strings.push(0);
+        address
+    };
+
+    let mut words = Vec::new();
+    words.push(args.len());
+    words.extend(args.iter().map(|arg| push_string(arg)));
+    words.push(0);
+    words.extend(env.iter().map(|var| push_string(var)));
+    words.push(0);
+    words.extend([AT_PAGESZ, PAGE_SIZE, AT_ENTRY, entry_address, AT_NULL, 0]);
+
+    let mut content: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    content.extend_from_slice(&strings);
+    content.resize(size, 0);
+    content
+}
+
+/// Fails with [`SysError::OutOfMemory`] if there is not enough memory for the segments.
+pub fn load_elf(elf_file: &ElfFile, args: &[&str], env: &[&str]) -> Result<LoadedElf, SysError> {
     let mut page_tables = RootPageTableHolder::new_with_kernel_mapping();
 
     let elf_header = elf_file.get_header();
+    let entry_address = elf_header.entry_point as usize;
     let mut allocated_pages = Vec::new();
 
-    // Map 4KB stack
-    let mut stack = PinnedHeapPages::new(1);
+    // Map 4KB stack plus the space needed for the arguments. The rest of the stack
+    // is mapped on demand when the process accesses it.
+    let initial_stack_size = initial_stack_size(args, env);
+    let stack_size_in_pages = minimum_amount_of_pages(PAGE_SIZE + initial_stack_size);
+    let stack_size = stack_size_in_pages * PAGE_SIZE;
+    let stack_bottom = STACK_END - (stack_size - PAGE_SIZE);
+    let stack_pointer = stack_bottom + stack_size - initial_stack_size;
+
+    let mut stack_content = vec![0; stack_size - initial_stack_size];
+    stack_content.extend(build_initial_stack(args, env, entry_address, stack_pointer));
+
+    let mut stack = PinnedHeapPages::new(stack_size_in_pages)?;
+    stack.fill(&stack_content);
     let stack_addr = stack.addr();
     allocated_pages.push(stack);
 
     page_tables.map_userspace(
-        STACK_END,
+        stack_bottom,
         stack_addr.get(),
-        PAGE_SIZE,
+        stack_size,
         crate::memory::page_tables::XWRMode::ReadWrite,
         "Stack",
     );
@@ -48,7 +110,7 @@ pub fn load_elf(elf_file: &ElfFile) -> LoadedElf {
         let real_size = program_header.memory_size;
         let size_in_pages = minimum_amount_of_pages(real_size as usize);
 
-        let mut pages = PinnedHeapPages::new(size_in_pages);
+        let mut pages = PinnedHeapPages::new(size_in_pages)?;
         pages.fill(data);
 
         let pages_addr = pages.addr();
@@ -64,9 +126,50 @@ pub fn load_elf(elf_file: &ElfFile) -> LoadedElf {
         );
     }
 
-    LoadedElf {
-        entry_address: elf_header.entry_point as usize,
+    Ok(LoadedElf {
+        entry_address,
+        stack_pointer,
+        stack_bottom,
         page_tables,
         allocated_pages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_initial_stack, AT_ENTRY, AT_NULL, AT_PAGESZ};
+    use crate::memory::PAGE_SIZE;
+
+    #[test_case]
+    fn initial_stack_layout() {
+        const STACK_POINTER: usize = 0x1000;
+        let content = build_initial_stack(&["echo", "hi"], &["A=B"], 0x4242, STACK_POINTER);
+        assert_eq!(content.len() % 16, 0);
+
+        let words: alloc::vec::Vec<usize> = content
+            .chunks_exact(8)
+            .take(12)
+            .map(|chunk| usize::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let strings = STACK_POINTER + 12 * 8;
+
+        assert_eq!(
+            words,
+            [
+                2,
+                strings,
+                strings + 5,
+                0,
+                strings + 8,
+                0,
+                AT_PAGESZ,
+                PAGE_SIZE,
+                AT_ENTRY,
+                0x4242,
+                AT_NULL,
+                0
+            ]
+        );
+        assert_eq!(&content[12 * 8..12 * 8 + 12], b"echo\0hi\0A=B\0");
     }
 }
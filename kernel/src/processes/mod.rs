@@ -1,5 +1,9 @@
+pub mod address_space;
+pub mod fd_table;
+pub mod futex;
 mod loader;
 pub mod process;
 pub mod process_table;
 pub mod scheduler;
+pub mod signal;
 pub mod timer;
@@ -1,21 +1,30 @@
 use crate::{
     debug,
     klibc::elf::ElfFile,
-    memory::{page::PinnedHeapPages, page_tables::RootPageTableHolder, PAGE_SIZE},
-    net::sockets::SharedAssignedSocket,
-    processes::loader::{self, LoadedElf},
+    processes::{
+        address_space::AddressSpace,
+        fd_table::FileDescriptorTable,
+        loader::{self, LoadedElf},
+        signal::SignalState,
+        timer,
+    },
 };
 use alloc::{
-    collections::{BTreeMap, BTreeSet},
+    collections::BTreeSet,
     string::{String, ToString},
-    vec::Vec,
+    sync::Arc,
 };
 use common::{
-    net::UDPDescriptor,
-    syscalls::trap_frame::{Register, TrapFrame},
+    mutex::{Mutex, MutexGuard},
+    signal::Signal,
+    syscalls::{
+        trap_frame::{Register, TrapFrame},
+        CpuTimes, ExitStatus, Resource, ResourceLimit, SysError, UNLIMITED,
+    },
 };
 use core::{
     fmt::Debug,
+    ops::AddAssign,
     sync::atomic::{AtomicU64, Ordering},
 };
 
@@ -23,7 +32,17 @@ pub type Pid = u64;
 
 pub const NEVER_PID: Pid = 0;
 
-const FREE_MMAP_START_ADDRESS: usize = 0x2000000000;
+pub const NICE_MIN: i8 = -20;
+pub const NICE_MAX: i8 = 19;
+
+// Weight of a process for every nice value from -20 to 19 (same as Linux).
+// Every nice level changes the share of cpu time by roughly 10%.
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+const NICE_0_WEIGHT: u64 = 1024;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessState {
@@ -31,6 +50,92 @@ pub enum ProcessState {
     Waiting,
 }
 
+/// Consumed cpu time (in clocks) split by the mode the cpu was in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTime {
+    pub user: u64,
+    pub kernel: u64,
+}
+
+impl CpuTime {
+    pub fn user(clocks: u64) -> Self {
+        Self {
+            user: clocks,
+            kernel: 0,
+        }
+    }
+
+    pub fn kernel(clocks: u64) -> Self {
+        Self {
+            user: 0,
+            kernel: clocks,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.user + self.kernel
+    }
+
+    pub fn to_milliseconds(self) -> CpuTimes {
+        CpuTimes {
+            user: timer::clocks_to_milliseconds(self.user),
+            kernel: timer::clocks_to_milliseconds(self.kernel),
+        }
+    }
+}
+
+impl AddAssign for CpuTime {
+    fn add_assign(&mut self, other: Self) {
+        self.user += other.user;
+        self.kernel += other.kernel;
+    }
+}
+
+/// Limits enforced by the scheduler. The memory limits are kept by the address space
+/// and the descriptor limit by the descriptor table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceLimits {
+    // In milliseconds
+    pub cpu_time: usize,
+    pub children: usize,
+    // Indexed by the resource number. They only ever go down.
+    hard: [usize; Resource::COUNT],
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            cpu_time: UNLIMITED,
+            children: 64,
+            hard: [UNLIMITED; Resource::COUNT],
+        }
+    }
+}
+
+impl ResourceLimits {
+    pub fn hard(&self, resource: Resource) -> usize {
+        self.hard[resource.number()]
+    }
+
+    /// Records the new hard limit and the soft limits kept here.
+    /// The caller applies the other soft limits.
+    pub fn set(&mut self, resource: Resource, limit: ResourceLimit) -> Result<(), SysError> {
+        if limit.soft > limit.hard {
+            return Err(SysError::InvalidArgument);
+        }
+        if limit.hard > self.hard(resource) {
+            return Err(SysError::PermissionDenied);
+        }
+        self.hard[resource.number()] = limit.hard;
+        match resource {
+            Resource::CPU_TIME => self.cpu_time = limit.soft,
+            Resource::CHILDREN => self.children = limit.soft,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 fn get_next_pid() -> Pid {
     // PIDs will start from 1
     // 0 is reserved for the never process which will be never scheduled
@@ -40,19 +145,37 @@ fn get_next_pid() -> Pid {
     next_pid
 }
 
+/// A thread of execution. Threads of the same process share the address space
+/// and the file descriptors, but have their own registers and scheduling state.
 pub struct Process {
     name: String,
     pid: Pid,
+    parent_pid: Pid,
+    // Pid of the main thread which is also the pid of the whole process
+    thread_group: Pid,
     register_state: TrapFrame,
-    page_table: RootPageTableHolder,
+    address_space: Arc<Mutex<AddressSpace>>,
     program_counter: usize,
-    allocated_pages: Vec<PinnedHeapPages>,
     state: ProcessState,
-    free_mmap_address: usize,
-    next_free_descriptor: u64,
-    open_udp_sockets: BTreeMap<UDPDescriptor, SharedAssignedSocket>,
+    file_descriptors: Arc<Mutex<FileDescriptorTable>>,
     in_kernel_mode: bool,
     notify_on_die: BTreeSet<Pid>,
+    signals: SignalState,
+    // Deadline (in clocks) of a running sys_sleep
+    sleep_deadline: Option<u64>,
+    // Set if another thread terminated the process while this thread was running
+    pending_exit: Option<ExitStatus>,
+    // Physical address of the futex the thread waits for
+    futex: Option<usize>,
+    nice: i8,
+    // Same for all threads of the process
+    limits: ResourceLimits,
+    // Consumed cpu time (in clocks) weighted by the nice value
+    vruntime: u64,
+    // The main thread also holds the cpu time of the exited threads
+    cpu_time: CpuTime,
+    // Cpu time of the children whose exit status was collected
+    children_cpu_time: CpuTime,
 }
 
 impl Debug for Process {
@@ -61,18 +184,18 @@ impl Debug for Process {
             f,
             "Process [
             PID: {},
+            Thread group: {},
             Registers: {:?},
-            Page Table: {:?},
+            Address space: {:?},
             Program Counter: {:#x},
-            Number of allocated pages: {},
             State: {:?},
             In kernel mode: {}
         ]",
             self.pid,
+            self.thread_group,
             self.register_state,
-            self.page_table,
+            *self.address_space.lock(),
             self.program_counter,
-            self.allocated_pages.len(),
             self.state,
             self.in_kernel_mode
         )
@@ -84,16 +207,24 @@ impl Process {
         Self {
             name: "never".to_string(),
             pid: NEVER_PID,
+            parent_pid: NEVER_PID,
+            thread_group: NEVER_PID,
             register_state: TrapFrame::zero(),
-            page_table: RootPageTableHolder::invalid(),
+            address_space: Arc::new(Mutex::new(AddressSpace::invalid())),
             program_counter: 0,
-            allocated_pages: Vec::new(),
             state: ProcessState::Waiting,
-            free_mmap_address: FREE_MMAP_START_ADDRESS,
-            next_free_descriptor: 0,
-            open_udp_sockets: BTreeMap::new(),
+            file_descriptors: Arc::new(Mutex::new(FileDescriptorTable::new())),
             in_kernel_mode: false,
             notify_on_die: BTreeSet::new(),
+            signals: SignalState::new(),
+            sleep_deadline: None,
+            pending_exit: None,
+            futex: None,
+            nice: 0,
+            limits: ResourceLimits::default(),
+            vruntime: 0,
+            cpu_time: CpuTime::default(),
+            children_cpu_time: CpuTime::default(),
         }
     }
 
@@ -101,19 +232,8 @@ impl Process {
         self.notify_on_die.iter()
     }
 
-    pub fn mmap_pages(&mut self, number_of_pages: usize) -> *mut u8 {
-        let pages = PinnedHeapPages::new(number_of_pages);
-        self.page_table.map_userspace(
-            self.free_mmap_address,
-            pages.as_ptr() as usize,
-            PAGE_SIZE * number_of_pages,
-            crate::memory::page_tables::XWRMode::ReadWrite,
-            "Heap",
-        );
-        self.allocated_pages.push(pages);
-        let ptr = core::ptr::without_provenance_mut(self.free_mmap_address);
-        self.free_mmap_address += number_of_pages * PAGE_SIZE;
-        ptr
+    pub fn mmap_pages(&mut self, number_of_pages: usize) -> Result<*mut u8, SysError> {
+        self.address_space.lock().mmap_pages(number_of_pages)
     }
 
     pub fn add_notify_on_die(&mut self, pid: Pid) {
@@ -144,8 +264,8 @@ impl Process {
         self.state = state;
     }
 
-    pub fn get_page_table(&self) -> &RootPageTableHolder {
-        &self.page_table
+    pub fn address_space(&self) -> MutexGuard<'_, AddressSpace> {
+        self.address_space.lock()
     }
 
     pub fn get_name(&self) -> &str {
@@ -156,10 +276,117 @@ impl Process {
         self.pid
     }
 
+    pub fn get_parent_pid(&self) -> Pid {
+        self.parent_pid
+    }
+
+    /// The parent changes if it terminates before this process.
+    pub fn set_parent_pid(&mut self, parent_pid: Pid) {
+        self.parent_pid = parent_pid;
+    }
+
+    pub fn get_thread_group(&self) -> Pid {
+        self.thread_group
+    }
+
+    pub fn is_main_thread(&self) -> bool {
+        self.pid == self.thread_group
+    }
+
     pub fn set_syscall_return_code(&mut self, return_code: usize) {
         self.register_state[Register::a0] = return_code;
     }
 
+    pub fn signals(&self) -> &SignalState {
+        &self.signals
+    }
+
+    pub fn signals_mut(&mut self) -> &mut SignalState {
+        &mut self.signals
+    }
+
+    /// Returns true if the process must be terminated because of the signal.
+    pub fn raise_signal(&mut self, signal: Signal) -> bool {
+        let terminate = self.signals.raise(signal);
+        // Waiting syscalls are restarted after the signal handler returns
+        if self.signals.has_deliverable() && self.state == ProcessState::Waiting {
+            self.state = ProcessState::Runnable;
+        }
+        terminate
+    }
+
+    pub fn get_nice(&self) -> i8 {
+        self.nice
+    }
+
+    pub fn set_nice(&mut self, nice: i8) {
+        assert!((NICE_MIN..=NICE_MAX).contains(&nice));
+        self.nice = nice;
+    }
+
+    pub fn get_limits(&self) -> ResourceLimits {
+        self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+
+    pub fn get_vruntime(&self) -> u64 {
+        self.vruntime
+    }
+
+    pub fn set_vruntime(&mut self, vruntime: u64) {
+        self.vruntime = vruntime;
+    }
+
+    /// Processes with a lower nice value age slower and therefore get more cpu time.
+    pub fn account_runtime(&mut self, cpu_time: CpuTime) {
+        let weight = NICE_TO_WEIGHT[(self.nice - NICE_MIN) as usize];
+        self.vruntime += cpu_time.total() * NICE_0_WEIGHT / weight;
+        self.cpu_time += cpu_time;
+    }
+
+    pub fn get_cpu_time(&self) -> CpuTime {
+        self.cpu_time
+    }
+
+    pub fn add_exited_thread_cpu_time(&mut self, cpu_time: CpuTime) {
+        self.cpu_time += cpu_time;
+    }
+
+    pub fn get_children_cpu_time(&self) -> CpuTime {
+        self.children_cpu_time
+    }
+
+    pub fn add_children_cpu_time(&mut self, cpu_time: CpuTime) {
+        self.children_cpu_time += cpu_time;
+    }
+
+    pub fn get_sleep_deadline(&self) -> Option<u64> {
+        self.sleep_deadline
+    }
+
+    pub fn set_sleep_deadline(&mut self, deadline: Option<u64>) {
+        self.sleep_deadline = deadline;
+    }
+
+    pub fn get_futex(&self) -> Option<usize> {
+        self.futex
+    }
+
+    pub fn set_futex(&mut self, physical_address: Option<usize>) {
+        self.futex = physical_address;
+    }
+
+    pub fn set_pending_exit(&mut self, status: ExitStatus) {
+        self.pending_exit = Some(status);
+    }
+
+    pub fn take_pending_exit(&mut self) -> Option<ExitStatus> {
+        self.pending_exit.take()
+    }
+
     pub fn set_in_kernel_mode(&mut self, in_kernel_mode: bool) {
         self.in_kernel_mode = in_kernel_mode;
     }
@@ -168,51 +395,147 @@ impl Process {
         self.in_kernel_mode
     }
 
-    pub fn from_elf(elf_file: &ElfFile, name: &str) -> Self {
+    /// The first argument is used as the name of the process.
+    /// Fails with [`SysError::OutOfMemory`] if the program does not fit into memory.
+    pub fn from_elf(
+        elf_file: &ElfFile,
+        args: &[&str],
+        env: &[&str],
+        parent_pid: Pid,
+    ) -> Result<Self, SysError> {
         debug!("Create process from elf file");
 
         let LoadedElf {
             entry_address,
+            stack_pointer,
+            stack_bottom,
             page_tables: page_table,
             allocated_pages,
-        } = loader::load_elf(elf_file);
+        } = loader::load_elf(elf_file, args, env)?;
 
         let mut register_state = TrapFrame::zero();
-        register_state[Register::sp] = loader::STACK_START;
+        register_state[Register::sp] = stack_pointer;
+        // Pass the initial stack also as first argument to _start
+        register_state[Register::a0] = stack_pointer;
+
+        let pid = get_next_pid();
+        Ok(Self {
+            name: args[0].into(),
+            pid,
+            parent_pid,
+            thread_group: pid,
+            register_state,
+            address_space: Arc::new(Mutex::new(AddressSpace::new(
+                page_table,
+                allocated_pages,
+                stack_bottom,
+            ))),
+            program_counter: entry_address,
+            state: ProcessState::Runnable,
+            file_descriptors: Arc::new(Mutex::new(FileDescriptorTable::with_console())),
+            in_kernel_mode: false,
+            notify_on_die: BTreeSet::new(),
+            signals: SignalState::new(),
+            sleep_deadline: None,
+            pending_exit: None,
+            futex: None,
+            nice: 0,
+            limits: ResourceLimits::default(),
+            vruntime: 0,
+            cpu_time: CpuTime::default(),
+            children_cpu_time: CpuTime::default(),
+        })
+    }
+
+    /// Creates a child process which shares the memory copy-on-write with this process.
+    /// The child starts at the given program counter with a syscall return value of 0.
+    /// Only the calling thread is copied into the child.
+    pub fn fork(&mut self, register_state: &TrapFrame, program_counter: usize) -> Self {
+        let pid = get_next_pid();
+        let mut child = Self {
+            name: self.name.clone(),
+            pid,
+            parent_pid: self.thread_group,
+            thread_group: pid,
+            register_state: *register_state,
+            address_space: Arc::new(Mutex::new(self.address_space.lock().fork())),
+            program_counter,
+            state: ProcessState::Runnable,
+            file_descriptors: Arc::new(Mutex::new(self.file_descriptors.lock().clone())),
+            in_kernel_mode: false,
+            notify_on_die: BTreeSet::new(),
+            signals: self.signals.fork(),
+            sleep_deadline: None,
+            pending_exit: None,
+            futex: None,
+            nice: self.nice,
+            limits: self.limits,
+            vruntime: self.vruntime,
+            cpu_time: CpuTime::default(),
+            children_cpu_time: CpuTime::default(),
+        };
+        child.set_syscall_return_code(0);
+        child
+    }
+
+    /// Creates a new thread of this process. The thread starts at entry with the
+    /// given stack and gets arg as its first argument.
+    pub fn create_thread(&self, entry: usize, stack: usize, arg: usize) -> Self {
+        let mut register_state = TrapFrame::zero();
+        register_state[Register::sp] = stack;
+        register_state[Register::a0] = arg;
 
         Self {
-            name: name.into(),
+            name: self.name.clone(),
             pid: get_next_pid(),
+            parent_pid: self.parent_pid,
+            thread_group: self.thread_group,
             register_state,
-            page_table,
-            program_counter: entry_address,
-            allocated_pages,
+            address_space: self.address_space.clone(),
+            program_counter: entry,
             state: ProcessState::Runnable,
-            free_mmap_address: FREE_MMAP_START_ADDRESS,
-            next_free_descriptor: 0,
-            open_udp_sockets: BTreeMap::new(),
+            file_descriptors: self.file_descriptors.clone(),
             in_kernel_mode: false,
             notify_on_die: BTreeSet::new(),
+            signals: self.signals.for_thread(),
+            sleep_deadline: None,
+            pending_exit: None,
+            futex: None,
+            nice: self.nice,
+            limits: self.limits,
+            vruntime: self.vruntime,
+            cpu_time: CpuTime::default(),
+            children_cpu_time: CpuTime::default(),
         }
     }
 
-    pub fn put_new_udp_socket(&mut self, socket: SharedAssignedSocket) -> UDPDescriptor {
-        let descriptor = UDPDescriptor::new(self.next_free_descriptor);
-        self.next_free_descriptor += 1;
+    /// Gives the process a writable page if the address belongs to a copy-on-write page.
+    /// Returns false if the address is not part of a copy-on-write page.
+    pub fn handle_copy_on_write_fault(&mut self, address: usize) -> Result<bool, SysError> {
+        self.address_space
+            .lock()
+            .handle_copy_on_write_fault(address)
+    }
+
+    /// Copies the data into the memory of the process whose page table must be active.
+    /// Fails if the memory is not mapped writable for the process.
+    pub fn write_userspace(&mut self, address: usize, data: &[u8]) -> Result<(), ()> {
+        self.address_space.lock().write_userspace(address, data)
+    }
 
-        assert!(
-            self.open_udp_sockets.insert(descriptor, socket).is_none(),
-            "Descriptor must be empty."
-        );
+    /// Copies memory of the process whose page table must be active into the buffer.
+    /// Fails if the memory is not mapped for the process.
+    pub fn read_userspace(&self, address: usize, buffer: &mut [u8]) -> Result<(), ()> {
+        self.address_space.lock().read_userspace(address, buffer)
+    }
 
-        descriptor
+    pub fn file_descriptors(&self) -> MutexGuard<'_, FileDescriptorTable> {
+        self.file_descriptors.lock()
     }
 
-    pub fn get_shared_udp_socket(
-        &mut self,
-        descriptor: UDPDescriptor,
-    ) -> Option<&mut SharedAssignedSocket> {
-        self.open_udp_sockets.get_mut(&descriptor)
+    /// Replaces the descriptors, e.g. with the ones of the parent on execute.
+    pub fn set_file_descriptors(&mut self, file_descriptors: FileDescriptorTable) {
+        self.file_descriptors = Arc::new(Mutex::new(file_descriptors));
     }
 }
 
@@ -220,51 +543,202 @@ impl Drop for Process {
     fn drop(&mut self) {
         debug!(
             "Drop process (PID: {}) (Allocated pages: {:?})",
-            self.pid, self.allocated_pages
+            self.pid,
+            self.address_space.lock().allocated_pages()
         );
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{
-        autogenerated::userspace_programs::PROG1, klibc::elf::ElfFile, memory::PAGE_SIZE,
-        processes::process::FREE_MMAP_START_ADDRESS,
+    use alloc::sync::Arc;
+    use common::syscalls::{
+        trap_frame::{Register, TrapFrame},
+        Resource, ResourceLimit, SysError,
     };
 
-    use super::Process;
+    use crate::{autogenerated::userspace_programs::PROG1, klibc::elf::ElfFile, memory::PAGE_SIZE};
+
+    use super::{CpuTime, Process, ResourceLimits, NEVER_PID};
+
+    #[test_case]
+    fn hard_limits_can_only_be_lowered() {
+        let mut limits = ResourceLimits::default();
+        let lowered = ResourceLimit { soft: 8, hard: 16 };
+        assert_eq!(limits.set(Resource::CHILDREN, lowered), Ok(()));
+        assert_eq!(limits.children, 8);
+        assert_eq!(limits.hard(Resource::CHILDREN), 16);
+
+        let above_hard = ResourceLimit { soft: 32, hard: 16 };
+        assert_eq!(
+            limits.set(Resource::CHILDREN, above_hard),
+            Err(SysError::InvalidArgument)
+        );
+        let raised = ResourceLimit { soft: 8, hard: 32 };
+        assert_eq!(
+            limits.set(Resource::CHILDREN, raised),
+            Err(SysError::PermissionDenied)
+        );
+        assert_eq!(limits.children, 8);
+        assert_eq!(limits.hard(Resource::CHILDREN), 16);
+    }
 
     #[test_case]
     fn create_process_from_elf() {
         let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
-        let _process = Process::from_elf(&elf, "prog1");
+        let _process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
     }
 
     #[test_case]
-    fn mmap_process() {
+    fn fork_process() {
         let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
-        let mut process = Process::from_elf(&elf, "prog1");
-        assert!(
-            process.free_mmap_address == FREE_MMAP_START_ADDRESS,
-            "Free MMAP Address must set to correct start"
+        let mut process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        let ptr = process.mmap_pages(1).unwrap();
+        // The page is only backed by memory once it is accessed
+        assert!(process
+            .address_space()
+            .write_physical(ptr as usize, &[1])
+            .is_ok());
+
+        let mut register_state = TrapFrame::zero();
+        register_state[Register::a0] = 42;
+        let mut child = process.fork(&register_state, 0x1000);
+
+        assert_ne!(child.get_pid(), process.get_pid());
+        assert_eq!(child.get_parent_pid(), process.get_pid());
+        assert_eq!(child.get_program_counter(), 0x1000);
+        assert_eq!(child.get_register_state()[Register::a0], 0);
+        assert_eq!(
+            child.address_space().allocated_pages().len(),
+            process.address_space().allocated_pages().len()
+        );
+        assert!(child
+            .address_space()
+            .allocated_pages()
+            .iter()
+            .all(|pages| pages.is_shared()));
+
+        let shared_page = process
+            .address_space()
+            .page_table()
+            .get_copy_on_write_page(ptr as usize);
+        assert!(shared_page.is_some());
+        assert_eq!(
+            child
+                .address_space()
+                .page_table()
+                .get_copy_on_write_page(ptr as usize),
+            shared_page
         );
-        let ptr = process.mmap_pages(1);
-        assert!(
-            ptr as usize == FREE_MMAP_START_ADDRESS,
-            "Returned pointer must have the value of the initial free mmap start address."
+
+        assert!(child.handle_copy_on_write_fault(ptr as usize).unwrap());
+        assert_eq!(
+            child
+                .address_space()
+                .page_table()
+                .get_copy_on_write_page(ptr as usize),
+            None
         );
-        assert!(
-            process.free_mmap_address == FREE_MMAP_START_ADDRESS + PAGE_SIZE,
-            "Free mmap address must have the value of the next free value"
+        assert_ne!(
+            child
+                .address_space()
+                .page_table()
+                .translate_userspace_address_to_physical_address(ptr as *const u8),
+            shared_page.map(|page| page as *const u8)
         );
-        let ptr = process.mmap_pages(2);
-        assert!(
-            ptr as usize == FREE_MMAP_START_ADDRESS + PAGE_SIZE,
-            "Returned pointer must have the value of the initial free mmap start address."
+
+        drop(child);
+        assert!(process.handle_copy_on_write_fault(ptr as usize).unwrap());
+        assert_eq!(
+            process
+                .address_space()
+                .page_table()
+                .translate_userspace_address_to_physical_address(ptr as *const u8),
+            shared_page.map(|page| page as *const u8)
         );
-        assert!(
-            process.free_mmap_address == FREE_MMAP_START_ADDRESS + (3 * PAGE_SIZE),
-            "Free mmap address must have the value of the next free value"
+    }
+
+    #[test_case]
+    fn write_and_read_userspace() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        let ptr = process.mmap_pages(2).unwrap() as usize;
+
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let address = ptr + PAGE_SIZE - 3;
+        assert!(process
+            .address_space()
+            .write_physical(address, &data)
+            .is_ok());
+
+        let mut buffer = [0; 8];
+        assert!(process
+            .address_space()
+            .read_physical(address, &mut buffer)
+            .is_ok());
+        assert_eq!(buffer, data);
+
+        assert!(process
+            .address_space()
+            .write_physical(ptr + 2 * PAGE_SIZE - 3, &data)
+            .is_err());
+        assert!(process
+            .address_space()
+            .read_physical(0, &mut buffer)
+            .is_err());
+    }
+
+    #[test_case]
+    fn threads_share_address_space() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+
+        let mut thread = process.create_thread(0x1000, 0x5000, 42);
+        assert_ne!(thread.get_pid(), process.get_pid());
+        assert_eq!(thread.get_thread_group(), process.get_pid());
+        assert!(process.is_main_thread());
+        assert!(!thread.is_main_thread());
+        assert_eq!(thread.get_program_counter(), 0x1000);
+        assert_eq!(thread.get_register_state()[Register::sp], 0x5000);
+        assert_eq!(thread.get_register_state()[Register::a0], 42);
+
+        // Memory mapped by one thread is visible to the other
+        let ptr = thread.mmap_pages(1).unwrap() as usize;
+        assert!(process
+            .address_space()
+            .write_physical(ptr, &[1, 2, 3])
+            .is_ok());
+        let mut buffer = [0; 3];
+        assert!(thread
+            .address_space()
+            .read_physical(ptr, &mut buffer)
+            .is_ok());
+        assert_eq!(buffer, [1, 2, 3]);
+    }
+
+    #[test_case]
+    fn account_runtime_with_nice() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+
+        process.account_runtime(CpuTime::user(1000));
+        assert_eq!(process.get_vruntime(), 1000);
+
+        process.set_nice(19);
+        process.account_runtime(CpuTime::kernel(1000));
+        assert_eq!(process.get_vruntime(), 1000 + 1000 * 1024 / 15);
+
+        process.set_vruntime(0);
+        process.set_nice(-20);
+        process.account_runtime(CpuTime::user(1000));
+        assert_eq!(process.get_vruntime(), 1000 * 1024 / 88761);
+        // The real cpu time does not depend on the nice value
+        assert_eq!(
+            process.get_cpu_time(),
+            CpuTime {
+                user: 2000,
+                kernel: 1000
+            }
         );
     }
 }
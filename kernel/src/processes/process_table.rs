@@ -1,101 +1,393 @@
-use alloc::{collections::BTreeMap, sync::Arc};
-use common::mutex::Mutex;
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use common::{
+    mutex::Mutex,
+    signal::Signal,
+    syscalls::{self, ChildExit, ExitStatus, ProcessInfo, ProcessTimes},
+};
 
 use crate::{debug, info};
 
-use super::process::{Pid, Process, ProcessState, NEVER_PID};
+use super::process::{CpuTime, Pid, Process, ProcessState, ResourceLimits, NEVER_PID};
 
 pub type ProcessRef = Arc<Mutex<Process>>;
 
+/// An exited process whose exit status was not yet collected by its parent
+struct Zombie {
+    name: String,
+    parent_pid: Pid,
+    status: ExitStatus,
+    // Includes the cpu time of its collected children
+    cpu_time: CpuTime,
+}
+
+/// An exited thread whose exit value was not yet collected by a join
+struct ExitedThread {
+    thread_group: Pid,
+    value: isize,
+}
+
+// Sleeping processes may be ahead of the others by this amount of vruntime (in clocks)
+// after a wakeup. This keeps interactive processes responsive without letting them
+// take over the cpu.
+const SLEEPER_CREDIT: u64 = 100_000;
+
 pub struct ProcessTable {
     processes: BTreeMap<Pid, ProcessRef>,
+    zombies: BTreeMap<Pid, Zombie>,
+    exited_threads: BTreeMap<Pid, ExitedThread>,
+    // Adopts the children of terminated processes
+    init_pid: Pid,
+    // Monotonically increasing lower bound of the vruntime of all runnable processes
+    min_vruntime: u64,
 }
 
 impl ProcessTable {
     pub fn new() -> Self {
         let mut self_ = Self {
             processes: BTreeMap::new(),
+            zombies: BTreeMap::new(),
+            exited_threads: BTreeMap::new(),
+            init_pid: NEVER_PID,
+            min_vruntime: 0,
         };
         self_.add_process(Process::never());
         self_
     }
 
-    pub fn add_process(&mut self, process: Process) {
+    pub fn add_process(&mut self, mut process: Process) {
+        // New processes must not get the cpu for longer than the already running ones
+        process.set_vruntime(process.get_vruntime().max(self.min_vruntime));
         self.processes
             .insert(process.get_pid(), Arc::new(Mutex::new(process)));
     }
 
+    /// Adds the first userspace process which adopts all orphans.
+    pub fn add_init_process(&mut self, process: Process) {
+        self.init_pid = process.get_pid();
+        self.add_process(process);
+    }
+
     pub fn is_empty(&self) -> bool {
         // If only the never process is left
         // we know the process table is empty
         self.processes.len() == 1
     }
 
-    pub fn get_highest_pid_without(&self, process_names: &[&str]) -> Option<Pid> {
-        self.processes
-            .iter()
-            .max_by_key(|(pid, _)| *pid)
-            .filter(|(_, p)| {
-                let p = p.lock();
-                !process_names.iter().any(|n| p.get_name() == *n) && p.get_pid() != NEVER_PID
-            })
-            .map(|(pid, _)| *pid)
-    }
-
     pub fn dump(&self) {
         for (pid, process) in &self.processes {
             let process = process.lock();
             info!(
-                "PID={} NAME={} STATE={:?}",
+                "PID={} NAME={} STATE={:?} NICE={} VRUNTIME={}",
                 *pid,
                 process.get_name(),
-                process.get_state()
+                process.get_state(),
+                process.get_nice(),
+                process.get_vruntime()
             );
         }
     }
 
-    pub fn kill(&mut self, pid: Pid) {
+    /// Describes every process including the zombies. The cpu time of all its threads is
+    /// added up. The process of the running thread is the given thread group.
+    pub fn list(&self, running: Pid) -> Vec<ProcessInfo> {
+        // Whether a thread is runnable and the total cpu time keyed by thread group
+        let mut activity: BTreeMap<Pid, (bool, CpuTime)> = BTreeMap::new();
+        for process in self.processes.values() {
+            let process = process.lock();
+            let (runnable, cpu_time) = activity.entry(process.get_thread_group()).or_default();
+            *runnable |= process.get_state() == ProcessState::Runnable;
+            *cpu_time += process.get_cpu_time();
+        }
+
+        let mut processes: Vec<ProcessInfo> = self
+            .processes
+            .values()
+            .filter_map(|process| {
+                let process = process.lock();
+                let pid = process.get_pid();
+                if pid == NEVER_PID || !process.is_main_thread() {
+                    return None;
+                }
+                let (runnable, cpu_time) = activity[&pid];
+                let state = if pid == running {
+                    syscalls::ProcessState::Running
+                } else if runnable {
+                    syscalls::ProcessState::Runnable
+                } else {
+                    syscalls::ProcessState::Waiting
+                };
+                let resident_pages = process.address_space().resident_pages();
+                Some(ProcessInfo::new(
+                    pid,
+                    process.get_parent_pid(),
+                    process.get_name(),
+                    state,
+                    cpu_time.to_milliseconds(),
+                    resident_pages as u64,
+                ))
+            })
+            .collect();
+        processes.extend(self.zombies.iter().map(|(pid, zombie)| {
+            ProcessInfo::new(
+                *pid,
+                zombie.parent_pid,
+                &zombie.name,
+                syscalls::ProcessState::Zombie,
+                zombie.cpu_time.to_milliseconds(),
+                0,
+            )
+        }));
+        processes.sort_by_key(|process| process.pid);
+        processes
+    }
+
+    /// Cpu time of all threads of the process and of its collected children.
+    pub fn times(&self, thread_group: Pid) -> ProcessTimes {
+        let mut children = CpuTime::default();
+        if let Some(main_thread) = self.processes.get(&thread_group) {
+            children = main_thread.lock().get_children_cpu_time();
+        }
+        ProcessTimes {
+            own: self.cpu_time(thread_group).to_milliseconds(),
+            children: children.to_milliseconds(),
+        }
+    }
+
+    /// Cpu time of all threads of the process.
+    pub fn cpu_time(&self, thread_group: Pid) -> CpuTime {
+        let mut cpu_time = CpuTime::default();
+        for process in self.processes.values() {
+            let process = process.lock();
+            if process.get_thread_group() == thread_group {
+                cpu_time += process.get_cpu_time();
+            }
+        }
+        cpu_time
+    }
+
+    pub fn set_limits(&self, thread_group: Pid, limits: ResourceLimits) {
+        for process in self.processes.values() {
+            let mut process = process.lock();
+            if process.get_thread_group() == thread_group {
+                process.set_limits(limits);
+            }
+        }
+    }
+
+    pub fn set_nice(&self, thread_group: Pid, nice: i8) {
+        for process in self.processes.values() {
+            let mut process = process.lock();
+            if process.get_thread_group() == thread_group {
+                process.set_nice(nice);
+            }
+        }
+    }
+
+    /// The process with the most resident pages. Init is never chosen because
+    /// the system cannot continue without it.
+    pub fn largest_process(&self) -> Option<Pid> {
+        self.processes
+            .values()
+            .filter_map(|process| {
+                let process = process.lock();
+                let pid = process.get_pid();
+                if pid == NEVER_PID || pid == self.init_pid || !process.is_main_thread() {
+                    return None;
+                }
+                let resident_pages = process.address_space().resident_pages();
+                Some((resident_pages, pid))
+            })
+            .max()
+            .map(|(_, pid)| pid)
+    }
+
+    /// Terminates all threads of the process the given thread belongs to.
+    pub fn kill(&mut self, pid: Pid, status: ExitStatus) {
         assert!(
             pid != NEVER_PID,
             "We are not allowed to kill the never process"
         );
-        debug!("Removing pid={pid} from process table");
-        if let Some(process) = self.processes.remove(&pid) {
-            assert_eq!(
-                Arc::strong_count(&process),
-                1,
-                "There should no more than one process handles be active"
+        let Some(thread_group) = self.get_thread_group(pid) else {
+            return;
+        };
+        debug!("Removing thread group={thread_group} from process table");
+
+        let threads: Vec<Pid> = self
+            .processes
+            .iter()
+            .filter(|(_, p)| p.lock().get_thread_group() == thread_group)
+            .map(|(pid, _)| *pid)
+            .collect();
+        let name: String = self
+            .processes
+            .get(&thread_group)
+            .map(|main_thread| main_thread.lock().get_name().into())
+            .unwrap_or_default();
+        let mut parent_pid = NEVER_PID;
+        let mut cpu_time = CpuTime::default();
+        for thread in threads {
+            let (parent, thread_cpu_time) = self.remove_thread(thread);
+            parent_pid = parent;
+            cpu_time += thread_cpu_time;
+        }
+
+        self.exited_threads
+            .retain(|_, thread| thread.thread_group != thread_group);
+        self.reparent_children(thread_group);
+
+        if parent_pid == NEVER_PID {
+            return;
+        }
+        if let Some(parent) = self.processes.get(&parent_pid) {
+            parent.lock().raise_signal(Signal::SIGCHLD);
+            self.zombies.insert(
+                thread_group,
+                Zombie {
+                    name,
+                    parent_pid,
+                    status,
+                    cpu_time,
+                },
             );
-            for pid in process.lock().get_notifies_on_die() {
-                self.wake_process_up(*pid);
+        }
+    }
+
+    /// Init adopts the running and exited children of a terminated process
+    /// and collects their exit status.
+    fn reparent_children(&mut self, parent_pid: Pid) {
+        let init_alive = self.init_pid != NEVER_PID
+            && self.init_pid != parent_pid
+            && self.processes.contains_key(&self.init_pid);
+        let adopter = if init_alive { self.init_pid } else { NEVER_PID };
+
+        for process in self.processes.values() {
+            let mut process = process.lock();
+            if process.get_parent_pid() == parent_pid {
+                process.set_parent_pid(adopter);
+                if init_alive {
+                    // Init might already wait for any of its children
+                    process.add_notify_on_die(adopter);
+                }
             }
         }
+
+        if !init_alive {
+            // Nobody is left to collect the exit status
+            self.zombies
+                .retain(|_, zombie| zombie.parent_pid != parent_pid);
+            return;
+        }
+        let mut adopted_zombie = false;
+        for zombie in self.zombies.values_mut() {
+            if zombie.parent_pid == parent_pid {
+                zombie.parent_pid = adopter;
+                adopted_zombie = true;
+            }
+        }
+        if adopted_zombie {
+            self.processes[&adopter]
+                .lock()
+                .raise_signal(Signal::SIGCHLD);
+            self.wake_process_up(adopter);
+        }
     }
 
-    pub fn next_runnable(&self, old_pid: Pid) -> Option<ProcessRef> {
-        let mut next_iter = self
-            .processes
-            .range(old_pid..)
-            .skip(1)
-            .filter_map(Self::filter_map_runnable_processes);
+    /// Removes a single thread. The exit value is kept until the thread is joined.
+    pub fn exit_thread(&mut self, pid: Pid, value: isize) {
+        let Some(thread_group) = self.get_thread_group(pid) else {
+            return;
+        };
+        debug!("Removing thread pid={pid} from process table");
+        let (_, cpu_time) = self.remove_thread(pid);
+        if let Some(main_thread) = self.processes.get(&thread_group) {
+            main_thread.lock().add_exited_thread_cpu_time(cpu_time);
+        }
+        self.exited_threads.insert(
+            pid,
+            ExitedThread {
+                thread_group,
+                value,
+            },
+        );
+    }
+
+    /// Returns the exit value of an exited thread of the given thread group.
+    pub fn take_exited_thread(&mut self, thread_group: Pid, pid: Pid) -> Option<isize> {
+        if self.exited_threads.get(&pid)?.thread_group != thread_group {
+            return None;
+        }
+        self.exited_threads.remove(&pid).map(|thread| thread.value)
+    }
 
-        if let Some(next_process) = next_iter.next() {
-            Some(next_process.clone())
-        } else {
-            self.processes
-                .iter()
-                .filter_map(Self::filter_map_runnable_processes)
-                .next()
-                .cloned()
+    /// Returns the parent of the removed thread and the cpu time of the thread
+    /// including the one of its collected children.
+    fn remove_thread(&mut self, pid: Pid) -> (Pid, CpuTime) {
+        let process = self
+            .processes
+            .remove(&pid)
+            .expect("Thread must be in the process table");
+        assert_eq!(
+            Arc::strong_count(&process),
+            1,
+            "There should no more than one process handles be active"
+        );
+        let process = process.lock();
+        for pid in process.get_notifies_on_die() {
+            self.wake_process_up(*pid);
         }
+        let mut cpu_time = process.get_cpu_time();
+        cpu_time += process.get_children_cpu_time();
+        (process.get_parent_pid(), cpu_time)
     }
 
-    fn filter_map_runnable_processes<'a>((_, p): (&Pid, &'a ProcessRef)) -> Option<&'a ProcessRef> {
-        if p.lock().get_state() == ProcessState::Runnable {
-            Some(p)
-        } else {
-            None
+    pub fn get_thread_group(&self, pid: Pid) -> Option<Pid> {
+        self.processes
+            .get(&pid)
+            .map(|process| process.lock().get_thread_group())
+    }
+
+    /// Removes an exited child of the given parent from the table.
+    /// If no pid is given, any exited child is returned.
+    pub fn reap_child(&mut self, parent_pid: Pid, pid: Option<Pid>) -> Option<ChildExit> {
+        let child_pid = *self
+            .zombies
+            .iter()
+            .find(|(child_pid, zombie)| {
+                zombie.parent_pid == parent_pid && pid.is_none_or(|pid| pid == **child_pid)
+            })?
+            .0;
+        let zombie = self.zombies.remove(&child_pid)?;
+        if let Some(parent) = self.processes.get(&parent_pid) {
+            parent.lock().add_children_cpu_time(zombie.cpu_time);
         }
+        Some(ChildExit {
+            pid: child_pid,
+            status: zombie.status,
+        })
+    }
+
+    pub fn get_children(&self, parent_pid: Pid) -> impl Iterator<Item = &ProcessRef> {
+        self.processes.values().filter(move |p| {
+            let p = p.lock();
+            p.get_parent_pid() == parent_pid && p.is_main_thread()
+        })
+    }
+
+    /// Returns the runnable process with the lowest vruntime. The skipped process is
+    /// only returned if no other process is runnable.
+    pub fn next_runnable(&mut self, skip: Option<Pid>) -> Option<ProcessRef> {
+        let (_, vruntime, pid) = self
+            .processes
+            .iter()
+            .filter_map(|(pid, p)| {
+                let p = p.lock();
+                (p.get_state() == ProcessState::Runnable)
+                    .then(|| (Some(*pid) == skip, p.get_vruntime(), *pid))
+            })
+            .min()?;
+
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        self.processes.get(&pid).cloned()
     }
 
     pub fn get_process(&self, pid: Pid) -> Option<&ProcessRef> {
@@ -111,12 +403,231 @@ impl ProcessTable {
 
     pub fn wake_process_up(&self, pid: Pid) {
         debug!("Waking process up with pid={pid}");
-        let mut process = self.processes.get(&pid).expect("Process must exist").lock();
+        // Waiting syscalls are restarted after a wakeup and check their condition again.
+        // Therefore, it doesn't hurt to wake up a process which waits for something else.
+        if let Some(process) = self.processes.get(&pid) {
+            let mut process = process.lock();
+            if process.get_state() == ProcessState::Waiting {
+                let min_vruntime = self.min_vruntime.saturating_sub(SLEEPER_CREDIT);
+                let vruntime = process.get_vruntime().max(min_vruntime);
+                process.set_vruntime(vruntime);
+            }
+            process.set_state(ProcessState::Runnable);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::syscalls::{self, ChildExit, ExitStatus};
+
+    use crate::{
+        autogenerated::userspace_programs::PROG1, klibc::elf::ElfFile, memory::PAGE_SIZE,
+        processes::timer,
+    };
+
+    use super::{CpuTime, Process, ProcessState, ProcessTable, NEVER_PID};
+
+    #[test_case]
+    fn next_runnable_prefers_lowest_vruntime() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let mut first = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        first.set_vruntime(200);
+        let first_pid = first.get_pid();
+        process_table.add_process(first);
+
+        let mut second = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        second.set_vruntime(100);
+        let second_pid = second.get_pid();
+        process_table.add_process(second);
+
+        let next = process_table.next_runnable(None).unwrap();
+        assert_eq!(next.lock().get_pid(), second_pid);
+
+        // A yielding process only runs if nobody else wants to
+        let next = process_table.next_runnable(Some(second_pid)).unwrap();
+        assert_eq!(next.lock().get_pid(), first_pid);
+
+        // New processes start at the minimum vruntime
+        let third = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        let third_pid = third.get_pid();
+        process_table.add_process(third);
+        let third = process_table.get_process(third_pid).unwrap();
+        assert_eq!(third.lock().get_vruntime(), 200);
+    }
+
+    #[test_case]
+    fn list_adds_up_threads() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let mut process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        process.account_runtime(CpuTime::user(timer::milliseconds_to_clocks(20)));
+        let mut thread = process.create_thread(0x1000, 0x5000, 0);
+        thread.account_runtime(CpuTime::kernel(timer::milliseconds_to_clocks(10)));
+        thread.set_state(ProcessState::Waiting);
+        let pid = process.get_pid();
+        process_table.add_process(process);
+        process_table.add_process(thread);
+
+        let processes = process_table.list(NEVER_PID);
+        assert_eq!(processes.len(), 1);
+        let info = processes[0];
+        assert_eq!(info.pid, pid);
+        assert_eq!(info.parent_pid, NEVER_PID);
+        assert_eq!(info.name(), "prog1");
+        assert_eq!(info.state, syscalls::ProcessState::Runnable);
+        assert_eq!(info.user_time, 20);
+        assert_eq!(info.kernel_time, 10);
+        assert!(info.resident_pages > 0);
+    }
+
+    #[test_case]
+    fn list_shows_running_process_and_zombies() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let parent = Process::from_elf(&elf, &["parent"], &[], NEVER_PID).unwrap();
+        let parent_pid = parent.get_pid();
+        process_table.add_process(parent);
+        let exited = Process::from_elf(&elf, &["exited"], &[], parent_pid).unwrap();
+        let exited_pid = exited.get_pid();
+        process_table.add_process(exited);
+        process_table.kill(exited_pid, ExitStatus::Exited(1));
+
+        let processes = process_table.list(parent_pid);
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].pid, parent_pid);
+        assert_eq!(processes[0].state, syscalls::ProcessState::Running);
+        assert_eq!(processes[1].pid, exited_pid);
+        assert_eq!(processes[1].name(), "exited");
+        assert_eq!(processes[1].state, syscalls::ProcessState::Zombie);
+
+        assert!(process_table.reap_child(parent_pid, None).is_some());
+        assert_eq!(process_table.list(parent_pid).len(), 1);
+    }
+
+    #[test_case]
+    fn set_nice_applies_to_all_threads() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let process = Process::from_elf(&elf, &["prog1"], &[], NEVER_PID).unwrap();
+        let thread = process.create_thread(0x1000, 0x5000, 0);
+        let other = Process::from_elf(&elf, &["other"], &[], NEVER_PID).unwrap();
+        let (pid, thread_pid, other_pid) = (process.get_pid(), thread.get_pid(), other.get_pid());
+        process_table.add_process(process);
+        process_table.add_process(thread);
+        process_table.add_process(other);
+
+        process_table.set_nice(pid, 5);
+        let nice = |pid| process_table.get_process(pid).unwrap().lock().get_nice();
+        assert_eq!(nice(pid), 5);
+        assert_eq!(nice(thread_pid), 5);
+        assert_eq!(nice(other_pid), 0);
+    }
+
+    #[test_case]
+    fn init_adopts_orphans() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let init = Process::from_elf(&elf, &["init"], &[], NEVER_PID).unwrap();
+        let init_pid = init.get_pid();
+        process_table.add_init_process(init);
+        let parent = Process::from_elf(&elf, &["parent"], &[], init_pid).unwrap();
+        let parent_pid = parent.get_pid();
+        process_table.add_process(parent);
+        let running = Process::from_elf(&elf, &["running"], &[], parent_pid).unwrap();
+        let running_pid = running.get_pid();
+        process_table.add_process(running);
+        let exited = Process::from_elf(&elf, &["exited"], &[], parent_pid).unwrap();
+        let exited_pid = exited.get_pid();
+        process_table.add_process(exited);
+
+        process_table.kill(exited_pid, ExitStatus::Exited(1));
+        process_table.kill(parent_pid, ExitStatus::Exited(0));
+
+        let running = process_table.get_process(running_pid).unwrap();
+        assert_eq!(running.lock().get_parent_pid(), init_pid);
         assert_eq!(
-            process.get_state(),
-            ProcessState::Waiting,
-            "Process must be in waiting state to be woken up"
+            process_table.reap_child(init_pid, Some(exited_pid)),
+            Some(ChildExit {
+                pid: exited_pid,
+                status: ExitStatus::Exited(1)
+            })
         );
-        process.set_state(ProcessState::Runnable);
+        assert!(process_table
+            .reap_child(init_pid, Some(parent_pid))
+            .is_some());
+        assert_eq!(process_table.reap_child(init_pid, None), None);
+
+        process_table.kill(running_pid, ExitStatus::Exited(2));
+        assert!(process_table
+            .reap_child(init_pid, Some(running_pid))
+            .is_some());
+    }
+
+    #[test_case]
+    fn largest_process_spares_init() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let mut init = Process::from_elf(&elf, &["init"], &[], NEVER_PID).unwrap();
+        let init_pid = init.get_pid();
+        let ptr = init.mmap_pages(8).unwrap() as usize;
+        assert!(init
+            .address_space()
+            .write_physical(ptr, &[1; 8 * PAGE_SIZE])
+            .is_ok());
+        process_table.add_init_process(init);
+        assert_eq!(process_table.largest_process(), None);
+
+        let small = Process::from_elf(&elf, &["small"], &[], init_pid).unwrap();
+        process_table.add_process(small);
+        let mut large = Process::from_elf(&elf, &["large"], &[], init_pid).unwrap();
+        let large_pid = large.get_pid();
+        let ptr = large.mmap_pages(2).unwrap() as usize;
+        assert!(large
+            .address_space()
+            .write_physical(ptr, &[1; 2 * PAGE_SIZE])
+            .is_ok());
+        process_table.add_process(large);
+
+        assert_eq!(process_table.largest_process(), Some(large_pid));
+    }
+
+    #[test_case]
+    fn collected_children_add_their_cpu_time() {
+        let elf = ElfFile::parse(PROG1).expect("Cannot parse elf file");
+        let mut process_table = ProcessTable::new();
+
+        let parent = Process::from_elf(&elf, &["parent"], &[], NEVER_PID).unwrap();
+        let parent_pid = parent.get_pid();
+        process_table.add_process(parent);
+        let mut child = Process::from_elf(&elf, &["child"], &[], parent_pid).unwrap();
+        child.account_runtime(CpuTime::user(timer::milliseconds_to_clocks(5)));
+        let mut thread = child.create_thread(0x1000, 0x5000, 0);
+        thread.account_runtime(CpuTime::kernel(timer::milliseconds_to_clocks(3)));
+        let child_pid = child.get_pid();
+        let thread_pid = thread.get_pid();
+        process_table.add_process(child);
+        process_table.add_process(thread);
+
+        // The exited thread still counts for the process
+        process_table.exit_thread(thread_pid, 0);
+        assert_eq!(process_table.times(child_pid).own.kernel, 3);
+
+        process_table.kill(child_pid, ExitStatus::Exited(0));
+        // Only collected children count
+        assert_eq!(process_table.times(parent_pid).children.user, 0);
+        process_table
+            .reap_child(parent_pid, Some(child_pid))
+            .unwrap();
+        let times = process_table.times(parent_pid);
+        assert_eq!(times.children.user, 5);
+        assert_eq!(times.children.kernel, 3);
     }
 }
@@ -1,29 +1,65 @@
-use common::mutex::Mutex;
+use alloc::vec::Vec;
+use common::{
+    mutex::Mutex,
+    signal::Signal,
+    syscalls::{
+        trap_frame::Register, ChildExit, ExitStatus, ProcessInfo, ProcessTimes, SysError, UNLIMITED,
+    },
+};
 
 use crate::{
     autogenerated::userspace_programs::{INIT, PROGRAMS},
     cpu, debug, info,
     interrupts::{read_trap_frame, set_sscratch_to_kernel_trap_frame, write_trap_frame},
     klibc::{elf::ElfFile, macros::unwrap_or_return, runtime_initialized::RuntimeInitializedData},
-    memory::page_tables::{activate_page_table, KERNEL_PAGE_TABLES},
+    memory::{
+        self,
+        page_tables::{activate_page_table, KERNEL_PAGE_TABLES},
+    },
+    println,
     processes::{process::Process, timer},
     test::qemu_exit,
 };
 
 use super::{
-    process::{Pid, ProcessState},
+    futex::FutexTable,
+    process::{CpuTime, Pid, ProcessState, ResourceLimits, NEVER_PID, NICE_MAX, NICE_MIN},
     process_table::{ProcessRef, ProcessTable},
+    signal::{SignalDelivery, SignalFrame},
 };
 
 pub static THE: RuntimeInitializedData<Mutex<Scheduler>> = RuntimeInitializedData::new();
 
+// Wakeups of code which might run while the scheduler is locked,
+// e.g. a pipe which is closed because the process table drops its last process
+static DEFERRED_WAKEUPS: Mutex<Vec<Pid>> = Mutex::new(Vec::new());
+
+// Milliseconds a process may run after exceeding its cpu time limit before it is killed
+const CPU_TIME_GRACE_PERIOD: u64 = 1000;
+
 pub fn init() {
     THE.initialize(Mutex::new(Scheduler::new()));
 }
 
+/// Wakes the process up on the next schedule. Can be called while the scheduler is locked.
+pub fn wake_up_deferred(pid: Pid) {
+    DEFERRED_WAKEUPS.lock().push(pid);
+    if !timer::is_quantum_active() {
+        // Nobody is running which would reach the next schedule
+        timer::set_timer(0);
+    }
+}
+
 pub struct Scheduler {
     process_table: ProcessTable,
     current_process: ProcessRef,
+    // Receives SIGINT on Ctrl+C
+    foreground_process: Option<Pid>,
+    // Clock value of the last trap entry, trap exit or process switch
+    current_started_at: u64,
+    // The current process gave up the cpu voluntarily
+    yield_requested: bool,
+    futexes: FutexTable,
 }
 
 impl Scheduler {
@@ -32,13 +68,18 @@ impl Scheduler {
         let current_process = process_table.get_dummy_process();
 
         let elf = ElfFile::parse(INIT).expect("Cannot parse ELF file");
-        let process = Process::from_elf(&elf, "init");
-        process_table.add_process(process);
+        let process =
+            Process::from_elf(&elf, &["init"], &[], NEVER_PID).expect("Init must fit into memory");
+        process_table.add_init_process(process);
         info!("Scheduler initialized and INIT process added to queue");
 
         Self {
             process_table,
             current_process,
+            foreground_process: None,
+            current_started_at: 0,
+            yield_requested: false,
+            futexes: FutexTable::new(),
         }
     }
 
@@ -46,14 +87,55 @@ impl Scheduler {
         self.process_table.dump();
     }
 
+    pub fn list_processes(&self) -> Vec<ProcessInfo> {
+        let running = self.current_process.lock().get_thread_group();
+        self.process_table.list(running)
+    }
+
     pub fn get_current_process(&self) -> &ProcessRef {
         &self.current_process
     }
 
+    pub fn wake_up(&self, pid: Pid) {
+        self.process_table.wake_process_up(pid);
+    }
+
+    /// True if no process is running and the hart waits for interrupts.
+    pub fn is_idle(&self) -> bool {
+        self.current_process.lock().get_pid() == NEVER_PID
+    }
+
     pub fn get_process(&self, pid: Pid) -> Option<&ProcessRef> {
         self.process_table.get_process(pid)
     }
 
+    /// Lets other runnable processes run before the current one on the next schedule.
+    pub fn yield_current_process(&mut self) {
+        self.yield_requested = true;
+    }
+
+    pub fn is_yield_requested(&self) -> bool {
+        self.yield_requested
+    }
+
+    /// Sets the nice value of all threads of the given process or of the current process
+    /// if pid is 0.
+    pub fn set_priority(&mut self, pid: Pid, nice: isize) -> Result<(), SysError> {
+        let nice = i8::try_from(nice)
+            .ok()
+            .filter(|nice| (NICE_MIN..=NICE_MAX).contains(nice))
+            .ok_or(SysError::InvalidArgument)?;
+        let thread_group = if pid == NEVER_PID {
+            self.current_process.lock().get_thread_group()
+        } else {
+            self.process_table
+                .get_thread_group(pid)
+                .ok_or(SysError::NoSuchProcess)?
+        };
+        self.process_table.set_nice(thread_group, nice);
+        Ok(())
+    }
+
     pub fn schedule(&mut self) {
         debug!("Schedule next process");
         if self.prepare_next_process() {
@@ -69,60 +151,447 @@ impl Scheduler {
         set_sscratch_to_kernel_trap_frame();
     }
 
-    pub fn kill_current_process(&mut self) {
+    pub fn kill_current_process(&mut self, status: ExitStatus) {
         let current_process = self.swap_current_with_dummy();
 
         activate_page_table(&KERNEL_PAGE_TABLES);
         let pid = current_process.lock().get_pid();
         drop(current_process);
-        self.process_table.kill(pid);
+        self.kill(pid, status);
     }
 
-    pub fn let_current_process_wait_for(&self, pid: Pid) -> bool {
-        let wait_for_process = unwrap_or_return!(self.process_table.get_process(pid), false);
+    fn kill(&mut self, pid: Pid, status: ExitStatus) {
+        let Some(thread_group) = self.process_table.get_thread_group(pid) else {
+            return;
+        };
+        if self.foreground_process == Some(thread_group) {
+            self.foreground_process = None;
+        }
 
         let mut current_process = self.current_process.lock();
-        current_process.set_state(ProcessState::Waiting);
-        current_process.set_syscall_return_code(0);
+        if current_process.get_pid() != NEVER_PID
+            && current_process.get_thread_group() == thread_group
+        {
+            // The running thread cannot be removed here. It terminates the whole
+            // process as soon as it returns to userspace.
+            current_process.set_pending_exit(status);
+            return;
+        }
+        drop(current_process);
 
-        wait_for_process
-            .lock()
-            .add_notify_on_die(current_process.get_pid());
+        self.process_table.kill(pid, status);
+    }
 
+    /// Frees memory by killing the process with the most resident pages.
+    /// Returns false if there is no process which could be killed.
+    pub fn kill_largest_process(&mut self) -> bool {
+        let Some(pid) = self.process_table.largest_process() else {
+            return false;
+        };
+        if let Some(process) = self.process_table.get_process(pid) {
+            println!(
+                "Out of memory: killing process {} (PID: {pid})",
+                process.lock().get_name()
+            );
+        }
+        self.kill(pid, ExitStatus::Signaled(Signal::SIGKILL));
         true
     }
 
-    pub fn send_ctrl_c(&mut self) {
-        self.queue_current_process_back();
+    /// Must be called from a syscall. The thread starts at entry with the given stack.
+    pub fn create_thread(&mut self, entry: usize, stack: usize, arg: usize) -> Pid {
+        let thread = self.current_process.lock().create_thread(entry, stack, arg);
+        let pid = thread.get_pid();
+        self.process_table.add_process(thread);
+        pid
+    }
+
+    /// Exiting the main thread terminates the whole process.
+    pub fn exit_current_thread(&mut self, value: isize) {
+        let current_process = self.swap_current_with_dummy();
+
+        activate_page_table(&KERNEL_PAGE_TABLES);
+        let (pid, is_main_thread) =
+            current_process.with_lock(|p| (p.get_pid(), p.is_main_thread()));
+        drop(current_process);
+
+        if is_main_thread {
+            self.kill(pid, ExitStatus::Exited(value));
+        } else {
+            self.process_table.exit_thread(pid, value);
+        }
+    }
+
+    /// Returns the exit value of the thread or None if the thread is still running.
+    /// In the latter case the current thread is woken up as soon as it exits.
+    pub fn join_thread(&mut self, pid: Pid) -> Result<Option<isize>, SysError> {
+        let (current_pid, thread_group) = self
+            .current_process
+            .with_lock(|p| (p.get_pid(), p.get_thread_group()));
+        if pid == current_pid || pid == thread_group {
+            return Err(SysError::NoSuchProcess);
+        }
+
+        if let Some(value) = self.process_table.take_exited_thread(thread_group, pid) {
+            return Ok(Some(value));
+        }
+
+        let thread = self
+            .process_table
+            .get_process(pid)
+            .filter(|thread| thread.lock().get_thread_group() == thread_group)
+            .ok_or(SysError::NoSuchProcess)?;
+        thread.lock().add_notify_on_die(current_pid);
+        Ok(None)
+    }
+
+    /// Collects the exit status of an exited child of the current process.
+    /// Returns [`SysError::WouldBlock`] if the child is still running.
+    pub fn reap_child(&mut self, pid: Option<Pid>) -> Result<ChildExit, SysError> {
+        let parent_pid = self.current_process.lock().get_thread_group();
+
+        if let Some(child_exit) = self.process_table.reap_child(parent_pid, pid) {
+            return Ok(child_exit);
+        }
+
+        let has_running_child = self
+            .process_table
+            .get_children(parent_pid)
+            .any(|child| pid.is_none_or(|pid| child.lock().get_pid() == pid));
+
+        if has_running_child {
+            Err(SysError::WouldBlock)
+        } else {
+            Err(SysError::NoChildren)
+        }
+    }
+
+    /// Lets the current process sleep until one of the given children exits.
+    /// If no pid is given, any child will wake it up.
+    pub fn let_current_process_wait_for_children(&mut self, pid: Option<Pid>) {
+        let mut current_process = self.current_process.lock();
+        current_process.set_state(ProcessState::Waiting);
+
+        for child in self
+            .process_table
+            .get_children(current_process.get_thread_group())
+        {
+            let mut child = child.lock();
+            if pid.is_none_or(|pid| child.get_pid() == pid) {
+                child.add_notify_on_die(current_process.get_pid());
+            }
+        }
+    }
+
+    /// Lets the current thread wait until the futex is woken up. Returns true once
+    /// it was woken up. Must be called again after every wakeup of the thread.
+    pub fn futex_wait(&mut self, address: usize, expected: u32) -> Result<bool, SysError> {
+        let mut current_process = self.current_process.lock();
+        let pid = current_process.get_pid();
+
+        if let Some(physical_address) = current_process.get_futex() {
+            if self.futexes.is_waiting(physical_address, pid) {
+                // Woken up for another reason
+                current_process.set_state(ProcessState::Waiting);
+                return Ok(false);
+            }
+            current_process.set_futex(None);
+            return Ok(true);
+        }
+
+        let physical_address = futex_physical_address(&current_process, address)?;
+        let mut value = [0; 4];
+        current_process
+            .read_userspace(address, &mut value)
+            .map_err(|_| SysError::BadAddress)?;
+        if u32::from_ne_bytes(value) != expected {
+            return Err(SysError::WouldBlock);
+        }
+
+        self.futexes.enqueue(physical_address, pid);
+        current_process.set_futex(Some(physical_address));
+        current_process.set_state(ProcessState::Waiting);
+        Ok(false)
+    }
 
-        let highest_pid = self.process_table.get_highest_pid_without(&["yash"]);
+    /// Wakes up to count threads waiting on the futex and returns how many were woken.
+    pub fn futex_wake(&mut self, address: usize, count: usize) -> Result<usize, SysError> {
+        let physical_address = futex_physical_address(&self.current_process.lock(), address)?;
+
+        let mut woken = 0;
+        while woken < count {
+            let Some(pid) = self.futexes.dequeue(physical_address) else {
+                break;
+            };
+            // Threads which exited while waiting are still in the queue
+            if self.process_table.get_process(pid).is_some() {
+                self.process_table.wake_process_up(pid);
+                woken += 1;
+            }
+        }
+        Ok(woken)
+    }
 
-        if let Some(pid) = highest_pid {
-            activate_page_table(&KERNEL_PAGE_TABLES);
-            self.process_table.kill(pid);
+    pub fn send_ctrl_c(&mut self) {
+        if let Some(pid) = self.foreground_process {
+            let _ = self.send_signal(pid, Signal::SIGINT);
         }
 
         self.schedule();
     }
 
+    pub fn set_foreground_process(&mut self, pid: Pid) -> Result<(), SysError> {
+        if pid == NEVER_PID || self.process_table.get_process(pid).is_none() {
+            return Err(SysError::NoSuchProcess);
+        }
+        self.foreground_process = Some(pid);
+        Ok(())
+    }
+
+    pub fn send_signal(&mut self, pid: Pid, signal: Signal) -> Result<(), SysError> {
+        if pid == NEVER_PID {
+            return Err(SysError::NoSuchProcess);
+        }
+        let process = self
+            .process_table
+            .get_process(pid)
+            .ok_or(SysError::NoSuchProcess)?;
+
+        let terminate = process.lock().raise_signal(signal);
+
+        // The current process is terminated as soon as it returns to userspace
+        let current_pid = self.current_process.lock().get_pid();
+        if terminate && pid != current_pid {
+            self.kill(pid, ExitStatus::Signaled(signal));
+        }
+
+        Ok(())
+    }
+
+    /// Must be called right before returning to the current process. Pending signals
+    /// either terminate the process or let it continue in the signal handler.
+    pub fn deliver_signals(&mut self) {
+        loop {
+            let pending_exit = self.current_process.lock().take_pending_exit();
+            if let Some(status) = pending_exit {
+                self.kill_current_process(status);
+                self.schedule();
+                continue;
+            }
+
+            let delivery = self.current_process.with_lock(|mut p| {
+                if p.get_pid() == NEVER_PID {
+                    return None;
+                }
+                p.signals_mut().take_deliverable()
+            });
+
+            match delivery {
+                None => return,
+                Some(SignalDelivery::Handler {
+                    signal,
+                    handler,
+                    restorer,
+                }) => {
+                    if self.enter_signal_handler(signal, handler, restorer).is_ok() {
+                        return;
+                    }
+                    // There is no space for the signal frame on the stack
+                    self.kill_current_process(ExitStatus::Signaled(Signal::SIGSEGV));
+                    self.schedule();
+                }
+                Some(SignalDelivery::Terminate(signal)) => {
+                    self.kill_current_process(ExitStatus::Signaled(signal));
+                    self.schedule();
+                }
+            }
+        }
+    }
+
+    fn enter_signal_handler(
+        &mut self,
+        signal: Signal,
+        handler: usize,
+        restorer: usize,
+    ) -> Result<(), ()> {
+        let mut trap_frame = read_trap_frame();
+
+        self.current_process.with_lock(|mut p| {
+            let frame = SignalFrame {
+                trap_frame,
+                program_counter: cpu::read_sepc(),
+                blocked: p.signals().get_blocked(),
+            };
+            let frame_address =
+                trap_frame[Register::sp].wrapping_sub(core::mem::size_of::<SignalFrame>()) & !0xf;
+            p.write_userspace(frame_address, frame.as_bytes())?;
+
+            // The signal is blocked while its handler runs
+            let mut blocked = p.signals().get_blocked();
+            blocked.insert(signal);
+            p.signals_mut().set_blocked(blocked);
+
+            trap_frame[Register::sp] = frame_address;
+            trap_frame[Register::ra] = restorer;
+            trap_frame[Register::a0] = signal.number();
+            write_trap_frame(&trap_frame);
+            cpu::write_sepc(handler);
+
+            Ok(())
+        })
+    }
+
+    /// Restores the state of the current process from the signal frame on top of its stack.
+    pub fn return_from_signal_handler(&mut self) -> Result<(), ()> {
+        let trap_frame = read_trap_frame();
+
+        self.current_process.with_lock(|mut p| {
+            let mut frame = SignalFrame::zero();
+            p.read_userspace(trap_frame[Register::sp], frame.as_bytes_mut())?;
+
+            p.signals_mut().set_blocked(frame.blocked);
+            write_trap_frame(&frame.trap_frame);
+            cpu::write_sepc(frame.program_counter);
+
+            Ok(())
+        })
+    }
+
     pub fn get_dummy_process(&self) -> ProcessRef {
         self.process_table.get_dummy_process()
     }
 
-    pub fn start_program(&mut self, name: &str) -> Option<Pid> {
-        for (prog_name, elf) in PROGRAMS {
-            if name == *prog_name {
-                let elf = ElfFile::parse(elf).expect("Cannot parse ELF file");
-                let process = Process::from_elf(&elf, prog_name);
-                let pid = process.get_pid();
-                self.process_table.add_process(process);
-                return Some(pid);
-            }
+    /// The first argument is the name of the program.
+    pub fn start_program(&mut self, args: &[&str], env: &[&str]) -> Result<Pid, SysError> {
+        let name = *args.first().ok_or(SysError::NotFound)?;
+        let (_, elf) = PROGRAMS
+            .iter()
+            .find(|(prog_name, _)| name == *prog_name)
+            .ok_or(SysError::NotFound)?;
+        self.check_children_limit()?;
+
+        let elf = ElfFile::parse(elf).expect("Cannot parse ELF file");
+        let (parent_pid, nice, limits, file_descriptors, mapped_pages_limit, stack_limit_pages) =
+            self.current_process.with_lock(|p| {
+                let address_space = p.address_space();
+                (
+                    p.get_thread_group(),
+                    p.get_nice(),
+                    p.get_limits(),
+                    p.file_descriptors().for_new_program(),
+                    address_space.mapped_pages_limit(),
+                    address_space.stack_limit_pages(),
+                )
+            });
+        let mut process = Process::from_elf(&elf, args, env, parent_pid)?;
+        // Like on fork the priority, the limits and the file descriptors are inherited,
+        // except for the descriptors marked close-on-execute
+        process.set_nice(nice);
+        process.set_limits(limits);
+        process.set_file_descriptors(file_descriptors);
+        {
+            let mut address_space = process.address_space();
+            address_space.set_mapped_pages_limit(mapped_pages_limit);
+            address_space
+                .set_stack_limit_pages(stack_limit_pages)
+                .expect("Limit of the parent must be valid");
+        }
+        let pid = process.get_pid();
+        self.process_table.add_process(process);
+        Ok(pid)
+    }
+
+    pub fn fork_current_process(&mut self) -> Result<Pid, SysError> {
+        self.check_children_limit()?;
+        // The page tables of the child are allocated from the reserve of the kernel
+        if memory::is_starved() {
+            return Err(SysError::OutOfMemory);
+        }
+        let child = self
+            .current_process
+            .lock()
+            .fork(&read_trap_frame(), cpu::read_sepc() + 4);
+        let pid = child.get_pid();
+        self.process_table.add_process(child);
+        Ok(pid)
+    }
+
+    fn check_children_limit(&self) -> Result<(), SysError> {
+        let (thread_group, limits) = self
+            .current_process
+            .with_lock(|p| (p.get_thread_group(), p.get_limits()));
+        if self.process_table.get_children(thread_group).count() >= limits.children {
+            return Err(SysError::WouldBlock);
         }
-        None
+        Ok(())
+    }
+
+    pub fn get_current_limits(&self) -> ResourceLimits {
+        self.current_process.lock().get_limits()
+    }
+
+    /// Changes the limits of all threads of the current process.
+    pub fn set_current_limits(&mut self, limits: ResourceLimits) {
+        let thread_group = self.current_process.lock().get_thread_group();
+        self.process_table.set_limits(thread_group, limits);
+    }
+
+    /// Must be called on every trap entry. The time since the last trap exit
+    /// was spent in the mode the trap interrupted.
+    pub fn account_trap_entry(&mut self) {
+        self.account_current_process(!cpu::is_in_kernel_mode());
+    }
+
+    /// Must be called right before returning from a trap.
+    /// The time since the trap entry was spent in the kernel.
+    pub fn account_trap_exit(&mut self) {
+        self.account_current_process(false);
+    }
+
+    /// Charges the cpu time since the last accounting to the current process.
+    fn account_current_process(&mut self, in_userspace: bool) {
+        let now = timer::get_current_clocks();
+        let clocks = now.saturating_sub(core::mem::replace(&mut self.current_started_at, now));
+        let cpu_time = if in_userspace {
+            CpuTime::user(clocks)
+        } else {
+            CpuTime::kernel(clocks)
+        };
+        let (pid, thread_group, limits) = self.current_process.with_lock(|mut p| {
+            p.account_runtime(cpu_time);
+            (p.get_pid(), p.get_thread_group(), p.get_limits())
+        });
+        if pid != NEVER_PID && limits.cpu_time != UNLIMITED {
+            self.enforce_cpu_time_limit(thread_group, limits.cpu_time as u64, clocks);
+        }
+    }
+
+    /// The current process gets SIGXCPU once it exceeds its cpu time limit
+    /// and SIGKILL if it keeps running for another second.
+    fn enforce_cpu_time_limit(&mut self, thread_group: Pid, limit: u64, clocks: u64) {
+        let cpu_time = self.process_table.cpu_time(thread_group).total();
+        // Compared in milliseconds such that no limit can overflow the conversion
+        let current = timer::clocks_to_milliseconds(cpu_time);
+        let previous = timer::clocks_to_milliseconds(cpu_time.saturating_sub(clocks));
+        let crossed = |limit: u64| previous <= limit && current > limit;
+        let signal = if crossed(limit) {
+            Signal::SIGXCPU
+        } else if crossed(limit.saturating_add(CPU_TIME_GRACE_PERIOD)) {
+            Signal::SIGKILL
+        } else {
+            return;
+        };
+        self.current_process.lock().raise_signal(signal);
+    }
+
+    /// Cpu time of the current process and of its collected children.
+    pub fn current_process_times(&self) -> ProcessTimes {
+        let thread_group = self.current_process.lock().get_thread_group();
+        self.process_table.times(thread_group)
     }
 
     fn queue_current_process_back(&mut self) -> Pid {
+        self.account_current_process(false);
         self.swap_current_with_dummy().with_lock(|mut p| {
             p.set_program_counter(cpu::read_sepc());
             p.set_in_kernel_mode(cpu::is_in_kernel_mode());
@@ -136,12 +605,17 @@ impl Scheduler {
     fn prepare_next_process(&mut self) -> bool {
         let old_pid = self.queue_current_process_back();
 
+        for pid in core::mem::take(&mut *DEFERRED_WAKEUPS.lock()) {
+            self.process_table.wake_process_up(pid);
+        }
+
         if self.process_table.is_empty() {
             info!("No more processes to schedule, shutting down system");
             qemu_exit::exit_success();
         }
 
-        let next_process = unwrap_or_return!(self.process_table.next_runnable(old_pid), false);
+        let skip = core::mem::take(&mut self.yield_requested).then_some(old_pid);
+        let next_process = unwrap_or_return!(self.process_table.next_runnable(skip), false);
 
         next_process.with_lock(|p| {
             let pc = p.get_program_counter();
@@ -149,12 +623,13 @@ impl Scheduler {
             write_trap_frame(p.get_register_state());
             cpu::write_sepc(pc);
             cpu::set_ret_to_kernel_mode(p.get_in_kernel_mode());
-            activate_page_table(p.get_page_table());
+            activate_page_table(p.address_space().page_table());
 
             debug!("Scheduling PID={} NAME={}", p.get_pid(), p.get_name());
         });
 
         self.current_process = next_process;
+        self.current_started_at = timer::get_current_clocks();
 
         true
     }
@@ -164,3 +639,17 @@ impl Scheduler {
         core::mem::replace(&mut self.current_process, dummy_process)
     }
 }
+
+fn futex_physical_address(process: &Process, address: usize) -> Result<usize, SysError> {
+    if address % core::mem::align_of::<u32>() != 0 {
+        return Err(SysError::InvalidArgument);
+    }
+    let mut address_space = process.address_space();
+    // Otherwise the first write would move the word to another physical page
+    address_space.resolve_copy_on_write_range(address, core::mem::size_of::<u32>())?;
+    address_space
+        .page_table()
+        .translate_userspace_address_to_physical_address(address as *const u8)
+        .map(|physical_address| physical_address as usize)
+        .ok_or(SysError::BadAddress)
+}
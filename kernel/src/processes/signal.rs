@@ -0,0 +1,270 @@
+use alloc::sync::Arc;
+use common::{
+    mutex::Mutex,
+    signal::{DefaultAction, Signal, SignalSet, SIG_DFL, SIG_IGN},
+    syscalls::trap_frame::TrapFrame,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalAction {
+    Default,
+    Ignore,
+    Handler { handler: usize, restorer: usize },
+}
+
+impl SignalAction {
+    pub fn new(handler: usize, restorer: usize) -> Self {
+        match handler {
+            SIG_DFL => Self::Default,
+            SIG_IGN => Self::Ignore,
+            handler => Self::Handler { handler, restorer },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalDelivery {
+    Terminate(Signal),
+    Handler {
+        signal: Signal,
+        handler: usize,
+        restorer: usize,
+    },
+}
+
+/// Saved on the userspace stack while a signal handler runs.
+/// sys_sigreturn restores the process state from it.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalFrame {
+    pub trap_frame: TrapFrame,
+    pub program_counter: usize,
+    pub blocked: SignalSet,
+}
+
+impl SignalFrame {
+    pub fn zero() -> Self {
+        Self {
+            trap_frame: TrapFrame::zero(),
+            program_counter: 0,
+            blocked: SignalSet::empty(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The frame only consists of plain integers
+        unsafe {
+            core::slice::from_raw_parts(
+                self as *const Self as *const u8,
+                core::mem::size_of::<Self>(),
+            )
+        }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: The frame only consists of plain integers, so every bit pattern is valid
+        unsafe {
+            core::slice::from_raw_parts_mut(
+                self as *mut Self as *mut u8,
+                core::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
+
+/// Pending and blocked signals are kept per thread.
+pub struct SignalState {
+    pending: SignalSet,
+    blocked: SignalSet,
+    // Shared by all threads of the process
+    actions: Arc<Mutex<[SignalAction; Signal::COUNT]>>,
+}
+
+impl SignalState {
+    pub fn new() -> Self {
+        Self {
+            pending: SignalSet::empty(),
+            blocked: SignalSet::empty(),
+            actions: Arc::new(Mutex::new([SignalAction::Default; Signal::COUNT])),
+        }
+    }
+
+    /// A forked child gets a copy of the actions and keeps the blocked signals,
+    /// but nothing is pending.
+    pub fn fork(&self) -> Self {
+        Self {
+            pending: SignalSet::empty(),
+            blocked: self.blocked,
+            actions: Arc::new(Mutex::new(*self.actions.lock())),
+        }
+    }
+
+    /// A new thread shares the actions with the other threads of the process.
+    pub fn for_thread(&self) -> Self {
+        Self {
+            pending: SignalSet::empty(),
+            blocked: self.blocked,
+            actions: self.actions.clone(),
+        }
+    }
+
+    fn get_action(&self, signal: Signal) -> SignalAction {
+        self.actions.lock()[signal.number()]
+    }
+
+    fn is_ignored(&self, signal: Signal) -> bool {
+        match self.get_action(signal) {
+            SignalAction::Default => signal.default_action() == DefaultAction::Ignore,
+            SignalAction::Ignore => true,
+            SignalAction::Handler { .. } => false,
+        }
+    }
+
+    /// Marks the signal as pending. Returns true if the process must be terminated
+    /// by the signal, i.e. the signal is not blocked and has the default action.
+    pub fn raise(&mut self, signal: Signal) -> bool {
+        if self.is_ignored(signal) {
+            return false;
+        }
+        self.pending.insert(signal);
+        !self.blocked.contains(signal) && self.get_action(signal) == SignalAction::Default
+    }
+
+    pub fn set_action(&mut self, signal: Signal, action: SignalAction) -> Result<(), ()> {
+        if !signal.can_be_caught() {
+            return Err(());
+        }
+        self.actions.lock()[signal.number()] = action;
+        if self.is_ignored(signal) {
+            self.pending.remove(signal);
+        }
+        Ok(())
+    }
+
+    pub fn get_blocked(&self) -> SignalSet {
+        self.blocked
+    }
+
+    pub fn set_blocked(&mut self, mut blocked: SignalSet) {
+        blocked.remove(Signal::SIGKILL);
+        self.blocked = blocked;
+    }
+
+    pub fn has_deliverable(&self) -> bool {
+        self.pending.difference(self.blocked).first().is_some()
+    }
+
+    /// Removes the next pending signal which is not blocked.
+    pub fn take_deliverable(&mut self) -> Option<SignalDelivery> {
+        loop {
+            let signal = self.pending.difference(self.blocked).first()?;
+            self.pending.remove(signal);
+
+            if self.is_ignored(signal) {
+                continue;
+            }
+
+            return Some(match self.get_action(signal) {
+                SignalAction::Handler { handler, restorer } => SignalDelivery::Handler {
+                    signal,
+                    handler,
+                    restorer,
+                },
+                _ => SignalDelivery::Terminate(signal),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::signal::{Signal, SignalSet};
+
+    use super::{SignalAction, SignalDelivery, SignalState};
+
+    #[test_case]
+    fn default_actions() {
+        let mut state = SignalState::new();
+        assert!(!state.raise(Signal::SIGCHLD));
+        assert!(!state.has_deliverable());
+        assert!(state.raise(Signal::SIGINT));
+        assert_eq!(
+            state.take_deliverable(),
+            Some(SignalDelivery::Terminate(Signal::SIGINT))
+        );
+        assert_eq!(state.take_deliverable(), None);
+    }
+
+    #[test_case]
+    fn ignored_signal_is_discarded() {
+        let mut state = SignalState::new();
+        assert!(state.raise(Signal::SIGTERM));
+        assert!(state
+            .set_action(Signal::SIGTERM, SignalAction::Ignore)
+            .is_ok());
+        assert!(!state.raise(Signal::SIGTERM));
+        assert_eq!(state.take_deliverable(), None);
+    }
+
+    #[test_case]
+    fn blocked_signal_stays_pending() {
+        let mut state = SignalState::new();
+        state.set_blocked(SignalSet::all());
+        assert!(!state.raise(Signal::SIGUSR1));
+        assert_eq!(state.take_deliverable(), None);
+
+        // SIGKILL can never be blocked
+        assert!(state.raise(Signal::SIGKILL));
+        assert_eq!(
+            state.take_deliverable(),
+            Some(SignalDelivery::Terminate(Signal::SIGKILL))
+        );
+
+        state.set_blocked(SignalSet::empty());
+        assert_eq!(
+            state.take_deliverable(),
+            Some(SignalDelivery::Terminate(Signal::SIGUSR1))
+        );
+    }
+
+    #[test_case]
+    fn handler() {
+        let mut state = SignalState::new();
+        assert!(state
+            .set_action(Signal::SIGUSR2, SignalAction::new(0x1000, 0x2000))
+            .is_ok());
+        assert!(state
+            .set_action(Signal::SIGKILL, SignalAction::Ignore)
+            .is_err());
+        assert!(!state.raise(Signal::SIGUSR2));
+
+        let forked = state.fork();
+        assert!(!forked.has_deliverable());
+        let thread = state.for_thread();
+        assert!(!thread.has_deliverable());
+
+        assert_eq!(
+            state.take_deliverable(),
+            Some(SignalDelivery::Handler {
+                signal: Signal::SIGUSR2,
+                handler: 0x1000,
+                restorer: 0x2000
+            })
+        );
+    }
+
+    #[test_case]
+    fn threads_share_actions() {
+        let mut state = SignalState::new();
+        let mut thread = state.for_thread();
+        let mut forked = state.fork();
+        assert!(thread
+            .set_action(Signal::SIGUSR1, SignalAction::Ignore)
+            .is_ok());
+
+        assert!(!state.raise(Signal::SIGUSR1));
+        assert_eq!(state.take_deliverable(), None);
+        assert!(forked.raise(Signal::SIGUSR1));
+        assert!(!thread.raise(Signal::SIGUSR1));
+    }
+}
@@ -1,5 +1,9 @@
-use crate::{cpu, debug, device_tree, klibc::runtime_initialized::RuntimeInitializedData, sbi};
-use common::big_endian::BigEndian;
+use crate::{
+    cpu, debug, device_tree, klibc::runtime_initialized::RuntimeInitializedData,
+    processes::process::Pid, sbi,
+};
+use alloc::{collections::BTreeSet, vec::Vec};
+use common::{big_endian::BigEndian, mutex::Mutex};
 use core::arch::asm;
 
 pub const CLINT_BASE: usize = 0x2000000;
@@ -20,27 +24,151 @@ pub fn init() {
     CLOCKS_PER_SEC.initialize(clocks_per_sec);
 }
 
+static TIMER_QUEUE: Mutex<TimerQueue> = Mutex::new(TimerQueue::new());
+
+/// The hart has only one timer. Therefore, the deadline of the scheduling quantum
+/// and the wakeup deadlines of sleeping processes are multiplexed onto it.
+struct TimerQueue {
+    quantum_deadline: Option<u64>,
+    wakeups: BTreeSet<(u64, Pid)>,
+}
+
+impl TimerQueue {
+    const fn new() -> Self {
+        Self {
+            quantum_deadline: None,
+            wakeups: BTreeSet::new(),
+        }
+    }
+
+    fn next_deadline(&self) -> Option<u64> {
+        let next_wakeup = self.wakeups.first().map(|(deadline, _)| *deadline);
+        match (self.quantum_deadline, next_wakeup) {
+            (Some(quantum), Some(wakeup)) => Some(quantum.min(wakeup)),
+            (quantum, wakeup) => quantum.or(wakeup),
+        }
+    }
+
+    fn program(&self) {
+        if let Some(deadline) = self.next_deadline() {
+            // See below why u64::MAX cannot be used
+            sbi::extensions::timer_extension::sbi_set_timer(deadline.min(u64::MAX - 1))
+                .assert_success();
+            cpu::enable_timer_interrupt();
+        } else {
+            cpu::disable_timer_interrupt();
+            // Somehow u64::MAX triggers timer interrupt indefinitely
+            // u64::MAX - 1 works as intended.
+            sbi::extensions::timer_extension::sbi_set_timer(u64::MAX - 1).assert_success();
+        }
+    }
+}
+
+/// Saturates at u64::MAX such that durations from userspace cannot overflow.
+pub fn milliseconds_to_clocks(milliseconds: u64) -> u64 {
+    assert_eq!(*CLOCKS_PER_SEC / 1000, 10_000);
+    (*CLOCKS_PER_SEC / 1000)
+        .checked_mul(milliseconds)
+        .unwrap_or(u64::MAX)
+}
+
+pub fn clocks_to_milliseconds(clocks: u64) -> u64 {
+    clocks / (*CLOCKS_PER_SEC / 1000)
+}
+
+/// Sets the deadline of the current scheduling quantum.
 pub fn set_timer(milliseconds: u64) {
     debug!("enabling timer {milliseconds} ms");
-    let current = get_current_clocks();
-    assert_eq!(*CLOCKS_PER_SEC / 1000, 10_000);
-    let next = current + ((*CLOCKS_PER_SEC / 1000) * milliseconds);
-    sbi::extensions::timer_extension::sbi_set_timer(next).assert_success();
-    cpu::enable_timer_interrupt();
+    let mut queue = TIMER_QUEUE.lock();
+    queue.quantum_deadline =
+        Some(get_current_clocks().saturating_add(milliseconds_to_clocks(milliseconds)));
+    queue.program();
 }
 
+/// Disables the scheduling quantum. Wakeups still trigger the timer.
 pub fn disable_timer() {
     debug!("disabling timer");
-    cpu::disable_timer_interrupt();
-    // Somehow u64::MAX triggers timer interrupt indefinitely
-    // u64::MAX - 1 works as intended.
-    sbi::extensions::timer_extension::sbi_set_timer(u64::MAX - 1).assert_success();
+    let mut queue = TIMER_QUEUE.lock();
+    queue.quantum_deadline = None;
+    queue.program();
+}
+
+pub fn is_quantum_active() -> bool {
+    TIMER_QUEUE.lock().quantum_deadline.is_some()
+}
+
+/// The process is woken up as soon as the deadline (in clocks) is reached.
+pub fn add_wakeup(pid: Pid, deadline: u64) {
+    let mut queue = TIMER_QUEUE.lock();
+    queue.wakeups.insert((deadline, pid));
+    queue.program();
+}
+
+pub struct ExpiredDeadlines {
+    pub quantum_expired: bool,
+    pub wakeups: Vec<Pid>,
+}
+
+/// Removes all deadlines which are reached and reprograms the timer for the remaining ones.
+pub fn take_expired_deadlines() -> ExpiredDeadlines {
+    let now = get_current_clocks();
+    let mut queue = TIMER_QUEUE.lock();
+
+    let quantum_expired = queue
+        .quantum_deadline
+        .is_some_and(|deadline| deadline <= now);
+    if quantum_expired {
+        queue.quantum_deadline = None;
+    }
+
+    let mut wakeups = Vec::new();
+    while let Some(&(deadline, pid)) = queue.wakeups.first() {
+        if deadline > now {
+            break;
+        }
+        queue.wakeups.pop_first();
+        wakeups.push(pid);
+    }
+
+    queue.program();
+
+    ExpiredDeadlines {
+        quantum_expired,
+        wakeups,
+    }
 }
 
-fn get_current_clocks() -> u64 {
+pub fn get_current_clocks() -> u64 {
     let current: u64;
     unsafe {
         asm!("rdtime {current}", current = out(reg)current);
     };
     current
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{clocks_to_milliseconds, milliseconds_to_clocks, TimerQueue};
+
+    #[test_case]
+    fn next_deadline() {
+        let mut queue = TimerQueue::new();
+        assert_eq!(queue.next_deadline(), None);
+
+        queue.wakeups.insert((200, 1));
+        queue.wakeups.insert((100, 2));
+        assert_eq!(queue.next_deadline(), Some(100));
+
+        queue.quantum_deadline = Some(50);
+        assert_eq!(queue.next_deadline(), Some(50));
+
+        queue.quantum_deadline = Some(150);
+        assert_eq!(queue.next_deadline(), Some(100));
+    }
+
+    #[test_case]
+    fn milliseconds_to_clocks_saturates() {
+        assert_eq!(clocks_to_milliseconds(milliseconds_to_clocks(42)), 42);
+        assert_eq!(milliseconds_to_clocks(u64::MAX), u64::MAX);
+    }
+}
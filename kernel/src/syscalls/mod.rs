@@ -1,35 +1,48 @@
 mod validator;
 
-use core::ptr::{slice_from_raw_parts, slice_from_raw_parts_mut};
-
-use alloc::string::String;
+use alloc::{sync::Arc, vec::Vec};
 use common::{
-    net::UDPDescriptor,
+    signal::{Signal, SignalSet},
     syscalls::{
-        kernel::KernelSyscalls, userspace_argument::UserspaceArgument, SysExecuteError,
-        SysSocketError, SysWaitError,
+        kernel::KernelSyscalls, userspace_argument::UserspaceArgument, ChildExit, ExitStatus,
+        MemoryProtection, PipeDescriptors, ProcessInfo, ProcessTimes, ReceivedMessage, Resource,
+        ResourceLimit, SysError, NO_DESCRIPTOR,
     },
 };
 
 use crate::{
     autogenerated::userspace_programs::PROGRAMS,
     debug,
-    io::stdin_buf::STDIN_BUFFER,
-    klibc::macros::unwrap_or_return,
-    net::{udp::UdpHeader, ARP_CACHE, OPEN_UDP_SOCKETS},
+    io::{
+        channel::{Channel, MAX_MESSAGE_SIZE},
+        file::SharedFile,
+        pipe,
+    },
+    memory::{self, shared_memory::SharedMemory, PAGE_SIZE},
+    net::OPEN_UDP_SOCKETS,
     print, println,
     processes::{
         process::{Pid, ProcessState, NEVER_PID},
         process_table::ProcessRef,
         scheduler::{self},
+        signal::SignalAction,
+        timer,
     },
     syscalls::validator::UserspaceArgumentValidator,
 };
 
 use self::validator::{FailibleMutableSliceValidator, FailibleSliceValidator};
 
+// Reads and writes copy at most this much at once and return a short count,
+// such that a huge length does not need an equally huge kernel buffer
+const MAX_TRANSFER: usize = PAGE_SIZE;
+
 struct SyscallHandler {
     process_exit: bool,
+    // The process waits and executes the syscall again after it was woken up
+    restart_syscall: bool,
+    // The registers were restored from a signal frame and must not be overwritten
+    registers_restored: bool,
     current_process: ProcessRef,
     current_pid: Pid,
 }
@@ -40,10 +53,33 @@ impl SyscallHandler {
         let current_pid = current_process.lock().get_pid();
         Self {
             process_exit: false,
+            restart_syscall: false,
+            registers_restored: false,
             current_process,
             current_pid,
         }
     }
+
+    fn get_file(&self, descriptor: usize) -> Result<SharedFile, SysError> {
+        self.current_process
+            .lock()
+            .file_descriptors()
+            .get(descriptor)
+    }
+
+    /// Lets the current process sleep until the file changes and then restarts the syscall.
+    fn wait_for(&mut self, file: &SharedFile) {
+        file.register_wakeup(self.current_pid);
+        self.current_process.lock().set_state(ProcessState::Waiting);
+        self.restart_syscall = true;
+    }
+
+    fn exit_current_process(&mut self) {
+        // We don't want to overwrite the next process trap frame
+        self.process_exit = true;
+        self.current_process = scheduler::THE.lock().get_dummy_process();
+        self.current_pid = NEVER_PID;
+    }
 }
 
 impl KernelSyscalls for SyscallHandler {
@@ -56,169 +92,594 @@ impl KernelSyscalls for SyscallHandler {
     fn sys_panic(&mut self) {
         panic!("Userspace triggered kernel panic");
     }
-    fn sys_write_char(&mut self, c: UserspaceArgument<char>) {
-        print!("{}", c.validate());
-    }
+    fn sys_read(
+        &mut self,
+        descriptor: UserspaceArgument<usize>,
+        buffer: UserspaceArgument<&mut u8>,
+        length: UserspaceArgument<usize>,
+    ) -> Result<usize, SysError> {
+        let file = self.get_file(descriptor.validate())?;
+        let length = length.validate().min(MAX_TRANSFER);
+        if length == 0 {
+            return Ok(0);
+        }
+        let buffer = buffer.validate(length)?;
 
-    fn sys_read_input(&mut self) -> Option<u8> {
-        let mut stdin = STDIN_BUFFER.lock();
-        stdin.pop()
+        if !file.poll().readable {
+            self.wait_for(&file);
+            return Ok(0);
+        }
+
+        let mut data = memory::try_zeroed_buffer(buffer.len())?;
+        let count = file.read(&mut data)?;
+        buffer
+            .write(&data[..count])
+            .map_err(|_| SysError::BadAddress)?;
+        Ok(count)
     }
-    fn sys_read_input_wait(&mut self) -> u8 {
-        let input = STDIN_BUFFER.lock().pop();
-        if let Some(input) = input {
-            input
-        } else {
-            STDIN_BUFFER.lock().register_wakeup(self.current_pid);
-            self.current_process.lock().set_state(ProcessState::Waiting);
-            0
+
+    fn sys_write(
+        &mut self,
+        descriptor: UserspaceArgument<usize>,
+        buffer: UserspaceArgument<&u8>,
+        length: UserspaceArgument<usize>,
+    ) -> Result<usize, SysError> {
+        let file = self.get_file(descriptor.validate())?;
+        let length = length.validate();
+        if length == 0 {
+            return Ok(0);
+        }
+        if length > MAX_TRANSFER && file.keeps_message_boundaries() {
+            return Err(SysError::MessageTooLong);
         }
+        let data = buffer.validate(length.min(MAX_TRANSFER))?;
+
+        if !file.poll().writable {
+            self.wait_for(&file);
+            return Ok(0);
+        }
+
+        let result = file.write(&data);
+        if result == Err(SysError::BrokenPipe) {
+            self.current_process.lock().raise_signal(Signal::SIGPIPE);
+        }
+        result
+    }
+
+    fn sys_close(&mut self, descriptor: UserspaceArgument<usize>) -> Result<(), SysError> {
+        self.current_process
+            .lock()
+            .file_descriptors()
+            .close(descriptor.validate())
+    }
+
+    fn sys_dup2(
+        &mut self,
+        old_descriptor: UserspaceArgument<usize>,
+        new_descriptor: UserspaceArgument<usize>,
+    ) -> Result<usize, SysError> {
+        self.current_process
+            .lock()
+            .file_descriptors()
+            .dup2(old_descriptor.validate(), new_descriptor.validate())
     }
 
     fn sys_exit(&mut self, status: UserspaceArgument<isize>) {
-        // We don't want to overwrite the next process trap frame
-        self.process_exit = true;
-        self.current_process = scheduler::THE.lock().get_dummy_process();
-        self.current_pid = NEVER_PID;
+        self.exit_current_process();
 
-        debug!("Exit process with status: {}\n", status.validate());
-        scheduler::THE.lock().kill_current_process();
+        let status = status.validate();
+        debug!("Exit process with status: {}\n", status);
+        scheduler::THE
+            .lock()
+            .kill_current_process(ExitStatus::Exited(status));
     }
 
     fn sys_execute(
         &mut self,
-        name: UserspaceArgument<&u8>,
+        data: UserspaceArgument<&u8>,
         length: UserspaceArgument<usize>,
-    ) -> Result<u64, SysExecuteError> {
+        argc: UserspaceArgument<usize>,
+    ) -> Result<u64, SysError> {
         let length = length.validate();
+        let argc = argc.validate();
+        if length == 0 {
+            return Err(SysError::InvalidArgument);
+        }
 
-        if let Ok(physical_address) = name.validate(length) {
-            let slice = unsafe { &*slice_from_raw_parts(physical_address, length) };
-            let mut name = String::with_capacity(length);
-            for c in slice {
-                name.push(*c as char);
-            }
+        let data = data.validate(length)?;
 
-            if let Some(pid) = scheduler::THE.lock().start_program(&name) {
-                Ok(pid)
-            } else {
-                Err(SysExecuteError::InvalidProgram)
-            }
-        } else {
-            Err(SysExecuteError::InvalidPtr)
+        // Every string is null terminated
+        let strings = data
+            .strip_suffix(&[0])
+            .ok_or(SysError::InvalidArgument)?
+            .split(|c| *c == 0)
+            .map(core::str::from_utf8)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| SysError::InvalidArgument)?;
+
+        if argc == 0 || argc > strings.len() {
+            return Err(SysError::InvalidArgument);
         }
+        let (args, env) = strings.split_at(argc);
+
+        scheduler::THE.lock().start_program(args, env)
     }
 
-    fn sys_wait(&mut self, pid: UserspaceArgument<u64>) -> Result<(), SysWaitError> {
-        if scheduler::THE
+    fn sys_wait(
+        &mut self,
+        pid: UserspaceArgument<u64>,
+        non_blocking: UserspaceArgument<bool>,
+    ) -> Result<ChildExit, SysError> {
+        let pid = match pid.validate() {
+            0 => None,
+            pid => Some(pid),
+        };
+
+        let mut scheduler = scheduler::THE.lock();
+        let result = scheduler.reap_child(pid);
+        if matches!(result, Err(SysError::WouldBlock)) && !non_blocking.validate() {
+            scheduler.let_current_process_wait_for_children(pid);
+            self.restart_syscall = true;
+        }
+        result
+    }
+
+    fn sys_fork(&mut self) -> Result<u64, SysError> {
+        scheduler::THE.lock().fork_current_process()
+    }
+
+    fn sys_getpid(&mut self) -> u64 {
+        self.current_process.lock().get_thread_group()
+    }
+
+    fn sys_getppid(&mut self) -> u64 {
+        self.current_process.lock().get_parent_pid()
+    }
+
+    fn sys_kill(
+        &mut self,
+        pid: UserspaceArgument<u64>,
+        signal: UserspaceArgument<Signal>,
+    ) -> Result<(), SysError> {
+        let signal = signal.validate().ok_or(SysError::InvalidArgument)?;
+        scheduler::THE.lock().send_signal(pid.validate(), signal)
+    }
+
+    fn sys_sigaction(
+        &mut self,
+        signal: UserspaceArgument<Signal>,
+        handler: UserspaceArgument<usize>,
+        restorer: UserspaceArgument<usize>,
+    ) -> Result<(), SysError> {
+        let signal = signal.validate().ok_or(SysError::InvalidArgument)?;
+        let action = SignalAction::new(handler.validate(), restorer.validate());
+        self.current_process
             .lock()
-            .let_current_process_wait_for(pid.validate())
-        {
-            Ok(())
-        } else {
-            Err(SysWaitError::InvalidPid)
+            .signals_mut()
+            .set_action(signal, action)
+            .map_err(|_| SysError::InvalidArgument)
+    }
+
+    fn sys_sigprocmask(
+        &mut self,
+        block: UserspaceArgument<SignalSet>,
+        unblock: UserspaceArgument<SignalSet>,
+    ) -> SignalSet {
+        let mut process = self.current_process.lock();
+        let signals = process.signals_mut();
+        let old = signals.get_blocked();
+        signals.set_blocked(old.union(block.validate()).difference(unblock.validate()));
+        old
+    }
+
+    fn sys_sigreturn(&mut self) {
+        if scheduler::THE.lock().return_from_signal_handler().is_ok() {
+            self.registers_restored = true;
+            return;
+        }
+        // The signal frame was destroyed
+        self.exit_current_process();
+        scheduler::THE
+            .lock()
+            .kill_current_process(ExitStatus::Signaled(Signal::SIGSEGV));
+    }
+
+    fn sys_set_foreground(&mut self, pid: UserspaceArgument<u64>) -> Result<(), SysError> {
+        scheduler::THE.lock().set_foreground_process(pid.validate())
+    }
+
+    fn sys_sleep(&mut self, milliseconds: UserspaceArgument<u64>) {
+        let now = timer::get_current_clocks();
+        let mut process = self.current_process.lock();
+
+        // The syscall is restarted after every wakeup until the deadline is reached
+        let deadline = process.get_sleep_deadline().unwrap_or_else(|| {
+            // Sleeping for u64::MAX milliseconds sleeps forever
+            now.saturating_add(timer::milliseconds_to_clocks(milliseconds.validate()))
+        });
+        if now >= deadline {
+            process.set_sleep_deadline(None);
+            return;
+        }
+
+        process.set_sleep_deadline(Some(deadline));
+        process.set_state(ProcessState::Waiting);
+        timer::add_wakeup(self.current_pid, deadline);
+        self.restart_syscall = true;
+    }
+
+    fn sys_yield(&mut self) {
+        scheduler::THE.lock().yield_current_process();
+    }
+
+    fn sys_setpriority(
+        &mut self,
+        pid: UserspaceArgument<u64>,
+        nice: UserspaceArgument<isize>,
+    ) -> Result<(), SysError> {
+        scheduler::THE
+            .lock()
+            .set_priority(pid.validate(), nice.validate())
+    }
+
+    fn sys_thread_create(
+        &mut self,
+        entry: UserspaceArgument<usize>,
+        stack: UserspaceArgument<usize>,
+        arg: UserspaceArgument<usize>,
+    ) -> u64 {
+        scheduler::THE
+            .lock()
+            .create_thread(entry.validate(), stack.validate(), arg.validate())
+    }
+
+    fn sys_thread_exit(&mut self, value: UserspaceArgument<isize>) {
+        self.exit_current_process();
+        scheduler::THE.lock().exit_current_thread(value.validate());
+    }
+
+    fn sys_thread_join(&mut self, tid: UserspaceArgument<u64>) -> Result<isize, SysError> {
+        let value = scheduler::THE.lock().join_thread(tid.validate())?;
+        if let Some(value) = value {
+            return Ok(value);
         }
+        self.current_process.lock().set_state(ProcessState::Waiting);
+        self.restart_syscall = true;
+        // Discarded because the syscall is restarted
+        Ok(0)
     }
 
-    fn sys_mmap_pages(&mut self, number_of_pages: UserspaceArgument<usize>) -> *mut u8 {
+    fn sys_futex_wait(
+        &mut self,
+        address: UserspaceArgument<usize>,
+        expected: UserspaceArgument<u32>,
+    ) -> Result<(), SysError> {
+        let woken_up = scheduler::THE
+            .lock()
+            .futex_wait(address.validate(), expected.validate())?;
+        // The syscall is restarted after every wakeup until the futex was woken up
+        self.restart_syscall = !woken_up;
+        Ok(())
+    }
+
+    fn sys_futex_wake(
+        &mut self,
+        address: UserspaceArgument<usize>,
+        count: UserspaceArgument<usize>,
+    ) -> Result<usize, SysError> {
+        scheduler::THE
+            .lock()
+            .futex_wake(address.validate(), count.validate())
+    }
+
+    fn sys_mmap_pages(
+        &mut self,
+        number_of_pages: UserspaceArgument<usize>,
+    ) -> Result<*mut u8, SysError> {
         self.current_process
             .lock()
             .mmap_pages(number_of_pages.validate())
     }
 
-    fn sys_open_udp_socket(
+    fn sys_munmap(
         &mut self,
-        port: UserspaceArgument<u16>,
-    ) -> Result<UDPDescriptor, SysSocketError> {
-        let port = port.validate();
-        let socket = match OPEN_UDP_SOCKETS.lock().try_get_socket(port) {
-            None => return Err(SysSocketError::PortAlreadyUsed),
-            Some(socket) => socket,
-        };
-        Ok(self.current_process.lock().put_new_udp_socket(socket))
+        address: UserspaceArgument<usize>,
+        number_of_pages: UserspaceArgument<usize>,
+    ) -> Result<(), SysError> {
+        self.current_process
+            .lock()
+            .address_space()
+            .munmap(address.validate(), number_of_pages.validate())
+    }
+
+    fn sys_mprotect(
+        &mut self,
+        address: UserspaceArgument<usize>,
+        number_of_pages: UserspaceArgument<usize>,
+        protection: UserspaceArgument<MemoryProtection>,
+    ) -> Result<(), SysError> {
+        let protection = protection.validate().ok_or(SysError::InvalidArgument)?;
+        self.current_process.lock().address_space().mprotect(
+            address.validate(),
+            number_of_pages.validate(),
+            protection.into(),
+        )
+    }
+
+    fn sys_pipe(&mut self) -> Result<PipeDescriptors, SysError> {
+        let (reader, writer) = pipe::create();
+        let process = self.current_process.lock();
+        let mut file_descriptors = process.file_descriptors();
+        let read = file_descriptors.open(reader)?;
+        let write = file_descriptors.open(writer).inspect_err(|_| {
+            let _ = file_descriptors.close(read);
+        })?;
+        Ok(PipeDescriptors { read, write })
+    }
+
+    fn sys_shm_create(
+        &mut self,
+        number_of_pages: UserspaceArgument<usize>,
+    ) -> Result<usize, SysError> {
+        let memory = SharedMemory::new(number_of_pages.validate())?;
+        self.current_process
+            .lock()
+            .file_descriptors()
+            .open(Arc::new(memory))
     }
 
-    fn sys_write_back_udp_socket(
+    fn sys_shm_map(
         &mut self,
-        descriptor: UserspaceArgument<UDPDescriptor>,
+        descriptor: UserspaceArgument<usize>,
+        number_of_pages: UserspaceArgument<usize>,
+    ) -> Result<*mut u8, SysError> {
+        let file = self.get_file(descriptor.validate())?;
+        let pages = file.memory_pages().ok_or(SysError::InvalidArgument)?;
+        let pages = pages
+            .get(..number_of_pages.validate())
+            .ok_or(SysError::InvalidArgument)?;
+        self.current_process
+            .lock()
+            .address_space()
+            .mmap_shared(pages)
+    }
+
+    fn sys_open_udp_socket(&mut self, port: UserspaceArgument<u16>) -> Result<usize, SysError> {
+        let socket = OPEN_UDP_SOCKETS
+            .lock()
+            .try_get_socket(port.validate())
+            .ok_or(SysError::AddressInUse)?;
+        self.current_process.lock().file_descriptors().open(socket)
+    }
+
+    fn sys_channel_create(&mut self) -> Result<usize, SysError> {
+        self.current_process
+            .lock()
+            .file_descriptors()
+            .open(Arc::new(Channel::new()))
+    }
+
+    fn sys_channel_send(
+        &mut self,
+        descriptor: UserspaceArgument<usize>,
         buffer: UserspaceArgument<&u8>,
         length: UserspaceArgument<usize>,
-    ) -> Result<usize, SysSocketError> {
+        attached: UserspaceArgument<usize>,
+    ) -> Result<(), SysError> {
+        let file = self.get_file(descriptor.validate())?;
+        let attached = match attached.validate() {
+            NO_DESCRIPTOR => None,
+            attached => Some(self.get_file(attached)?),
+        };
         let length = length.validate();
-        let pa = buffer.validate(length);
-
-        self.current_process.with_lock(|mut p| {
-            let socket = unwrap_or_return!(
-                p.get_shared_udp_socket(descriptor.validate()),
-                Err(SysSocketError::InvalidDescriptor)
-            )
-            .lock();
-
-            let recv_ip = unwrap_or_return!(socket.get_from(), Err(SysSocketError::NoReceiveIPYet));
-            let recv_port = unwrap_or_return!(
-                socket.get_received_port(),
-                Err(SysSocketError::NoReceiveIPYet)
-            );
-
-            if let Ok(physical_address) = pa {
-                let slice = unsafe { &*slice_from_raw_parts(physical_address, length) };
-                // Get mac address of receiver
-                // Since we already received a packet we should have it in the cache
-                let destination_mac = *ARP_CACHE
-                    .lock()
-                    .get(&recv_ip)
-                    .expect("There must be a receiver mac already in the arp cache.");
-                let constructed_packet = UdpHeader::create_udp_packet(
-                    recv_ip,
-                    recv_port,
-                    destination_mac,
-                    socket.get_port(),
-                    slice,
-                );
-                crate::net::send_packet(constructed_packet);
-                Ok(length)
-            } else {
-                Err(SysSocketError::InvalidPtr)
-            }
-        })
+        // Checked before copying such that the length does not size a kernel buffer
+        if length > MAX_MESSAGE_SIZE {
+            return Err(SysError::MessageTooLong);
+        }
+        let data = if length == 0 {
+            Vec::new()
+        } else {
+            buffer.validate(length)?
+        };
+
+        if !file.poll().writable {
+            self.wait_for(&file);
+            return Ok(());
+        }
+
+        file.send_message(&data, attached)
     }
 
-    fn sys_read_udp_socket(
+    fn sys_channel_receive(
         &mut self,
-        descriptor: UserspaceArgument<UDPDescriptor>,
+        descriptor: UserspaceArgument<usize>,
         buffer: UserspaceArgument<&mut u8>,
         length: UserspaceArgument<usize>,
-    ) -> Result<usize, SysSocketError> {
-        // Process packets
-        crate::net::receive_and_process_packets();
-
+    ) -> Result<ReceivedMessage, SysError> {
+        let file = self.get_file(descriptor.validate())?;
         let length = length.validate();
-        let pa = buffer.validate(length);
-
-        self.current_process.with_lock(|mut p| {
-            let mut socket = unwrap_or_return!(
-                p.get_shared_udp_socket(descriptor.validate()),
-                Err(SysSocketError::InvalidDescriptor)
-            )
-            .lock();
-
-            if let Ok(physical_address) = pa {
-                let slice = unsafe { &mut *slice_from_raw_parts_mut(physical_address, length) };
-                Ok(socket.get_data(slice))
-            } else {
-                Err(SysSocketError::InvalidPtr)
+        let buffer = if length == 0 {
+            None
+        } else {
+            Some(buffer.validate(length)?)
+        };
+
+        if !file.poll().readable {
+            self.wait_for(&file);
+            return Ok(ReceivedMessage {
+                length: 0,
+                descriptor: None,
+            });
+        }
+
+        let mut data = memory::try_zeroed_buffer(length)?;
+        let mut descriptor = None;
+        let length = {
+            let process = self.current_process.lock();
+            let mut descriptors = process.file_descriptors();
+            // The message stays queued if there is no free descriptor for its file
+            file.receive_message(&mut data, &mut |attached| {
+                descriptor = Some(descriptors.open(attached)?);
+                Ok(())
+            })?
+        };
+        if let Some(buffer) = buffer {
+            if buffer.write(&data[..length]).is_err() {
+                // The process would never learn the descriptor
+                if let Some(descriptor) = descriptor {
+                    let _ = self
+                        .current_process
+                        .lock()
+                        .file_descriptors()
+                        .close(descriptor);
+                }
+                return Err(SysError::BadAddress);
             }
-        })
+        }
+        Ok(ReceivedMessage { length, descriptor })
+    }
+
+    fn sys_list_processes(
+        &mut self,
+        buffer: UserspaceArgument<&mut ProcessInfo>,
+        count: UserspaceArgument<usize>,
+    ) -> Result<usize, SysError> {
+        let processes = scheduler::THE.lock().list_processes();
+        let count = count.validate().min(processes.len());
+        if count > 0 {
+            let buffer = buffer.validate(count)?;
+            // SAFETY: ProcessInfo has no padding
+            let data = unsafe { as_bytes(&processes[..count]) };
+            buffer.write(data).map_err(|_| SysError::BadAddress)?;
+        }
+        Ok(processes.len())
+    }
+
+    fn sys_times(&mut self, times: UserspaceArgument<&mut ProcessTimes>) -> Result<u64, SysError> {
+        let buffer = times.validate(1)?;
+        let times = [scheduler::THE.lock().current_process_times()];
+        // SAFETY: ProcessTimes has no padding
+        let data = unsafe { as_bytes(&times) };
+        buffer.write(data).map_err(|_| SysError::BadAddress)?;
+        Ok(timer::clocks_to_milliseconds(timer::get_current_clocks()))
+    }
+
+    fn sys_setrlimit(
+        &mut self,
+        resource: UserspaceArgument<Resource>,
+        soft: UserspaceArgument<usize>,
+        hard: UserspaceArgument<usize>,
+    ) -> Result<(), SysError> {
+        let resource = resource.validate().ok_or(SysError::InvalidArgument)?;
+        let limit = ResourceLimit {
+            soft: soft.validate(),
+            hard: hard.validate(),
+        };
+        let mut scheduler = scheduler::THE.lock();
+        let mut limits = scheduler.get_current_limits();
+        limits.set(resource, limit)?;
+        {
+            let process = self.current_process.lock();
+            match resource {
+                Resource::MAPPED_PAGES => {
+                    process.address_space().set_mapped_pages_limit(limit.soft)
+                }
+                Resource::STACK_PAGES => {
+                    process.address_space().set_stack_limit_pages(limit.soft)?
+                }
+                Resource::DESCRIPTORS => process.file_descriptors().set_limit(limit.soft),
+                // Kept by the limits themselves
+                _ => {}
+            }
+        }
+        scheduler.set_current_limits(limits);
+        Ok(())
+    }
+
+    fn sys_getrlimit(
+        &mut self,
+        resource: UserspaceArgument<Resource>,
+        limit: UserspaceArgument<&mut ResourceLimit>,
+    ) -> Result<(), SysError> {
+        let resource = resource.validate().ok_or(SysError::InvalidArgument)?;
+        let buffer = limit.validate(1)?;
+        let process = self.current_process.lock();
+        let limits = process.get_limits();
+        let soft = match resource {
+            Resource::MAPPED_PAGES => process.address_space().mapped_pages_limit(),
+            Resource::STACK_PAGES => process.address_space().stack_limit_pages(),
+            Resource::DESCRIPTORS => process.file_descriptors().get_limit(),
+            Resource::CPU_TIME => limits.cpu_time,
+            Resource::CHILDREN => limits.children,
+            _ => return Err(SysError::InvalidArgument),
+        };
+        let limit = [ResourceLimit {
+            soft,
+            hard: limits.hard(resource),
+        }];
+        drop(process);
+        // SAFETY: ResourceLimit has no padding
+        let data = unsafe { as_bytes(&limit) };
+        buffer.write(data).map_err(|_| SysError::BadAddress)?;
+        Ok(())
+    }
+
+    fn sys_dup(
+        &mut self,
+        descriptor: UserspaceArgument<usize>,
+        close_on_execute: UserspaceArgument<bool>,
+    ) -> Result<usize, SysError> {
+        self.current_process
+            .lock()
+            .file_descriptors()
+            .dup(descriptor.validate(), close_on_execute.validate())
+    }
+}
+
+/// # Safety
+/// T must not contain padding, otherwise uninitialized bytes would be read.
+unsafe fn as_bytes<T>(values: &[T]) -> &[u8] {
+    unsafe {
+        core::slice::from_raw_parts(values.as_ptr() as *const u8, core::mem::size_of_val(values))
     }
 }
 
-pub fn handle_syscall(nr: usize, arg1: usize, arg2: usize, arg3: usize) -> Option<(usize, usize)> {
+pub fn handle_syscall(nr: usize, args: [usize; 6]) -> Option<(usize, usize)> {
     let mut handler = SyscallHandler::new();
-    let result = handler.dispatch(nr, arg1, arg2, arg3);
+    let Some(result) = handler.dispatch(nr, args) else {
+        debug!("Unknown syscall number {nr}");
+        // Encoded like the error of a Result
+        return Some((1, SysError::NotImplemented.code()));
+    };
 
-    if handler.process_exit {
+    if handler.process_exit || handler.restart_syscall || handler.registers_restored {
         None
     } else {
         Some(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use common::syscalls::{SysError, Syscalls};
+
+    use super::handle_syscall;
+
+    #[test_case]
+    fn unknown_syscall_numbers_are_rejected() {
+        assert!(Syscalls::from_number(Syscalls::sys_write as usize).is_some());
+        assert!(Syscalls::from_number(usize::MAX).is_none());
+        assert_eq!(
+            handle_syscall(usize::MAX, [0; 6]),
+            Some((1, SysError::NotImplemented.code()))
+        );
+    }
+
+    #[test_case]
+    fn error_codes_match_errno() {
+        assert_eq!(SysError::InvalidArgument.code(), 22);
+        assert_eq!(SysError::from_code(38), Some(SysError::NotImplemented));
+        assert_eq!(SysError::from_code(0), None);
+        assert_eq!(SysError::NoChildren.description(), "No child processes");
+    }
+}
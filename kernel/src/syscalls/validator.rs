@@ -1,16 +1,30 @@
+use alloc::vec::Vec;
 use common::{
-    net::UDPDescriptor,
-    syscalls::userspace_argument::{UserspaceArgument, UserspaceArgumentValueExtractor},
+    signal::{Signal, SignalSet},
+    syscalls::{
+        userspace_argument::{UserspaceArgument, UserspaceArgumentValueExtractor},
+        MemoryProtection, Resource, SysError,
+    },
 };
 
-use crate::processes::scheduler;
+use crate::{
+    memory::{
+        self,
+        user_access::{copy_from_user, copy_to_user},
+    },
+    processes::scheduler,
+};
 
-pub trait FailibleSliceValidator<'a, T: 'a> {
-    fn validate(self, len: usize) -> Result<&'a T, ()>;
+/// Copies a buffer out of the memory of the current process.
+/// Fails with [`SysError::BadAddress`] or if there is no memory for the copy.
+pub trait FailibleSliceValidator {
+    fn validate(self, len: usize) -> Result<Vec<u8>, SysError>;
 }
 
-pub trait FailibleMutableSliceValidator<'a, T: 'a> {
-    fn validate(self, len: usize) -> Result<&'a mut T, ()>;
+/// Checks that the current process can write to a buffer of len elements.
+/// The data is copied into it with [`UserspaceBuffer::write`].
+pub trait FailibleMutableSliceValidator {
+    fn validate(self, len: usize) -> Result<UserspaceBuffer, SysError>;
 }
 
 pub trait UserspaceArgumentValidator<T> {
@@ -27,58 +41,79 @@ macro_rules! simple_type {
     };
 }
 
+simple_type!(bool);
 simple_type!(char);
 simple_type!(u16);
+simple_type!(u32);
 simple_type!(usize);
 simple_type!(isize);
 simple_type!(u64);
-simple_type!(UDPDescriptor);
+simple_type!(SignalSet);
 
-impl<'a> FailibleSliceValidator<'a, u8> for UserspaceArgument<&'a u8> {
-    fn validate(self, len: usize) -> Result<&'a u8, ()> {
-        let current_process = scheduler::THE.lock().get_current_process().clone();
+impl UserspaceArgumentValidator<Option<Signal>> for UserspaceArgument<Signal> {
+    fn validate(self) -> Option<Signal> {
+        Signal::new(self.get().number())
+    }
+}
 
-        current_process.with_lock(|p| {
-            let page_table = p.get_page_table();
+impl UserspaceArgumentValidator<Option<MemoryProtection>> for UserspaceArgument<MemoryProtection> {
+    fn validate(self) -> Option<MemoryProtection> {
+        MemoryProtection::new(self.get().bits())
+    }
+}
 
-            let addr = self.get() as *const u8;
-            let last = addr.wrapping_add(len - 1);
+impl UserspaceArgumentValidator<Option<Resource>> for UserspaceArgument<Resource> {
+    fn validate(self) -> Option<Resource> {
+        Resource::new(self.get().number())
+    }
+}
 
-            if page_table
-                .translate_userspace_address_to_physical_address(last)
-                .is_none()
-            {
-                return Err(());
-            }
+impl FailibleSliceValidator for UserspaceArgument<&u8> {
+    fn validate(self, len: usize) -> Result<Vec<u8>, SysError> {
+        let current_process = scheduler::THE.lock().get_current_process().clone();
+        let address = self.get() as *const u8 as usize;
 
-            page_table
-                .translate_userspace_address_to_physical_address(addr)
-                .map(|ptr| unsafe { &*ptr })
-                .ok_or(())
-        })
+        // Check first such that a bogus length does not exhaust the kernel heap
+        current_process.with_lock(|p| p.address_space().check_readable(address, len))?;
+        let mut buffer = memory::try_zeroed_buffer(len)?;
+        copy_from_user(&mut buffer, address).map_err(|_| SysError::BadAddress)?;
+        Ok(buffer)
     }
 }
-impl<'a> FailibleMutableSliceValidator<'a, u8> for UserspaceArgument<&'a mut u8> {
-    fn validate(self, len: usize) -> Result<&'a mut u8, ()> {
+
+impl<T> FailibleMutableSliceValidator for UserspaceArgument<&mut T> {
+    fn validate(self, len: usize) -> Result<UserspaceBuffer, SysError> {
         let current_process = scheduler::THE.lock().get_current_process().clone();
+        let address = self.get() as *mut T as usize;
+        let len = len
+            .checked_mul(core::mem::size_of::<T>())
+            .ok_or(SysError::BadAddress)?;
 
-        current_process.with_lock(|p| {
-            let page_table = p.get_page_table();
+        current_process.with_lock(|p| p.address_space().check_writable(address, len))?;
+        Ok(UserspaceBuffer {
+            address,
+            length: len,
+        })
+    }
+}
 
-            let addr = self.get() as *mut u8;
-            let last = addr.wrapping_add(len - 1);
+/// A buffer in the memory of the current process which was writable during validation.
+pub struct UserspaceBuffer {
+    address: usize,
+    length: usize,
+}
 
-            if page_table
-                .translate_userspace_address_to_physical_address(last)
-                .is_none()
-            {
-                return Err(());
-            }
+impl UserspaceBuffer {
+    pub fn len(&self) -> usize {
+        self.length
+    }
 
-            page_table
-                .translate_userspace_address_to_physical_address(addr)
-                .map(|ptr| unsafe { &mut *(ptr as *mut _) })
-                .ok_or(())
-        })
+    /// Copies the data to the start of the buffer.
+    /// Fails if the data does not fit or the memory was unmapped in the meantime.
+    pub fn write(&self, data: &[u8]) -> Result<(), ()> {
+        if data.len() > self.length {
+            return Err(());
+        }
+        copy_to_user(self.address, data)
     }
 }
@@ -65,3 +65,26 @@ async fn execute_different_programs() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn pass_arguments() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("echo Hello   World").await?;
+    assert_eq!(output, "Hello World\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pass_environment() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    yaos.run_prog("export FOO=bar").await?;
+    yaos.run_prog("export FOO=baz").await?;
+
+    let output = yaos.run_prog("env").await?;
+    assert_eq!(output, "SHELL=yash\nFOO=baz\n");
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
 mod basics;
 mod net;
 mod panic;
+mod processes;
 mod signals;
@@ -0,0 +1,275 @@
+use crate::infra::qemu::QemuInstance;
+
+#[tokio::test]
+async fn fork_copies_memory_on_write() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("fork").await?;
+    assert_eq!(output, "Child: counter=42\nParent: counter=0\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn crash_only_kills_faulting_process() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("crash").await?;
+    assert!(output.contains("Hello from Crash! Writing to an unmapped address"));
+    assert!(output.contains("Process crashed!"));
+    assert!(output.contains("Name: Store/AMO page fault"));
+    assert!(output.contains("stval: 0xdeadbeef"));
+    assert!(output.contains("Process name: crash"));
+    assert!(!output.contains("This should never be printed"));
+    assert!(output.contains("crashed with exception code 15"));
+
+    let output = yaos.run_prog("prog1").await?;
+    assert_eq!(output, "Hello from Prog1\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn wait_returns_exit_status() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("wait").await?;
+    assert_eq!(output, "Child exited with status 3\nNo more children\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sleep() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("sleep 200").await?;
+    assert_eq!(output, "");
+
+    let output = yaos.run_prog("sleep").await?;
+    assert_eq!(output, "Usage: sleep <milliseconds>\nexited with status 1\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn nice() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("nice 10 echo niced").await?;
+    assert_eq!(output, "niced\n");
+
+    let output = yaos.run_prog("nice 20 echo niced").await?;
+    assert_eq!(
+        output,
+        "Cannot set nice value 20: Invalid argument\nexited with status 1\n"
+    );
+
+    let output = yaos.run_prog("nice").await?;
+    assert_eq!(
+        output,
+        "Usage: nice <value> <program> [arguments]\nexited with status 1\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn threads() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("threads").await?;
+    assert_eq!(
+        output,
+        "Thread 1 counted 100\nThread 2 counted 200\nThread 3 counted 300\nMain thread counted 0\nAll threads counted 600\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mutex_and_condvar() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("sync").await?;
+    assert_eq!(
+        output,
+        "Counter is 400\nConsumed 1\nConsumed 2\nConsumed 3\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stack_grows_until_limit() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("stackoverflow").await?;
+    assert!(output.contains("Using 64KB of stack: 2080\n"));
+    assert!(output.contains("Recursing without end\n"));
+    assert!(output.contains("Stack overflow in process stackoverflow (PID: "));
+    assert!(output.contains("killed by SIGSEGV\n"));
+    assert!(!output.contains("This should never be printed"));
+
+    let output = yaos.run_prog("prog1").await?;
+    assert_eq!(output, "Hello from Prog1\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn mmap_memory_is_freed_and_protected() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("mmap").await?;
+    assert!(output.contains("Freed range reused: true\n"));
+    assert!(output.contains("New pages are zeroed: true\n"));
+    assert!(output.contains("Allocated 256MiB in total\n"));
+    assert!(output.contains("Read-only page contains 42\n"));
+    assert!(output.contains("Writing to read-only page\n"));
+    assert!(output.contains("crashed with exception code 15\n"));
+    assert!(!output.contains("This should never be printed"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pipeline_connects_programs() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("echo Hello Pipe | cat").await?;
+    assert_eq!(output, "Hello Pipe\n");
+
+    let output = yaos.run_prog("echo Three stages | cat | cat").await?;
+    assert_eq!(output, "Three stages\n");
+
+    let output = yaos.run_prog("echo | nonexisting").await?;
+    assert_eq!(output, "Error executing program: No such file or directory\n");
+
+    // The shell must still use the console afterwards
+    let output = yaos.run_prog("prog1").await?;
+    assert_eq!(output, "Hello from Prog1\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn shared_memory_between_processes() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("shm").await?;
+    assert_eq!(
+        output,
+        "Consumer received 65536 bytes: true\nProducer received answer: 42\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn channel_passes_descriptors() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("channel").await?;
+    assert_eq!(
+        output,
+        "Service received 'Hello service' with a descriptor\nReply: Hi client\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ps_lists_and_kill_terminates_background_job() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("sleep 100000 &").await?;
+    let pid = output
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+
+    let output = yaos.run_prog("ps").await?;
+    let job = output
+        .lines()
+        .find(|line| line.split_whitespace().next() == Some(pid.as_str()))
+        .expect("Background job must be listed");
+    assert!(job.ends_with(" sleep"));
+    assert!(output
+        .lines()
+        .any(|line| line.contains(" running ") && line.ends_with(" ps")));
+
+    let output = yaos.run_prog(&format!("kill {pid}")).await?;
+    assert_eq!(output, format!("[{pid}] killed by SIGTERM\n"));
+
+    let output = yaos.run_prog("kill 4242").await?;
+    assert_eq!(
+        output,
+        "Cannot send SIGTERM to 4242: No such process\nexited with status 1\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn init_adopts_orphans() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("orphan").await?;
+    assert_eq!(
+        output,
+        "Grandchild adopted by pid 1\nGrandchild is no child of ours: true\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn time_measures_program() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("time sleep 100").await?;
+    let times: Vec<u64> = output
+        .split_whitespace()
+        .filter_map(|part| part.strip_suffix("ms"))
+        .map(|time| time.parse().expect("Time must be a number"))
+        .collect();
+    assert!(output.starts_with("real "), "Unexpected output {output}");
+    assert_eq!(times.len(), 3);
+    // Sleeping consumes no cpu time
+    assert!(times[0] >= 100);
+    assert!(times[1] + times[2] < times[0]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rlimit_restricts_resources() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("rlimit").await?;
+    assert_eq!(
+        output,
+        "pipe: Too many open files\nmmap: Cannot allocate memory\nfork: Resource temporarily unavailable\nbusy child killed by SIGXCPU\nraise: Operation not permitted\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn out_of_memory_kills_largest_process() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("oom").await?;
+    assert!(output.starts_with("Reserving 4GiB: Cannot allocate memory\n"));
+    assert!(output.contains("Out of memory: killing process oom (PID: "));
+    assert!(output.ends_with("Memory hog killed by SIGKILL\nMemory is available again\n"));
+    assert!(!output.contains("This should never be printed"));
+
+    // The shell survived and can still start programs
+    let output = yaos.run_prog("prog1").await?;
+    assert_eq!(output, "Hello from Prog1\n");
+
+    Ok(())
+}
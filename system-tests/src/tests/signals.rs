@@ -34,3 +34,13 @@ async fn should_not_exit_yash() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn signal_handlers_and_default_actions() -> anyhow::Result<()> {
+    let mut yaos = QemuInstance::start().await?;
+
+    let output = yaos.run_prog("signal").await?;
+    assert_eq!(output, "Child received SIGUSR1\nChild killed by SIGKILL\n");
+
+    Ok(())
+}
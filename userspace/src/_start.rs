@@ -1,11 +1,18 @@
 use common::syscalls::sys_exit;
 
+use crate::{args, thread};
+
 unsafe extern "C" {
     fn main();
 }
 
+/// The kernel passes a pointer to the initial stack which contains
+/// argc, argv, envp and the auxiliary vector.
 #[unsafe(no_mangle)]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(initial_stack: *const usize) -> ! {
+    // First such that printing works everywhere
+    thread::init_main_thread();
+    args::init(initial_stack);
     unsafe {
         main();
     }
@@ -0,0 +1,53 @@
+use core::{
+    ffi::{c_char, CStr},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+static INITIAL_STACK: AtomicPtr<usize> = AtomicPtr::new(core::ptr::null_mut());
+
+pub(crate) fn init(initial_stack: *const usize) {
+    INITIAL_STACK.store(initial_stack as *mut usize, Ordering::Relaxed);
+}
+
+/// Iterates over a null terminated array of string pointers
+fn strings(mut pointer: *const *const c_char) -> impl Iterator<Item = &'static str> {
+    core::iter::from_fn(move || {
+        // SAFETY: The kernel places a valid null terminated array on the initial stack
+        // which is never overwritten.
+        let string = unsafe { *pointer };
+        if string.is_null() {
+            return None;
+        }
+        pointer = unsafe { pointer.add(1) };
+        let string = unsafe { CStr::from_ptr(string) };
+        Some(string.to_str().expect("Arguments must be valid UTF-8"))
+    })
+}
+
+fn argc() -> usize {
+    let initial_stack = INITIAL_STACK.load(Ordering::Relaxed);
+    assert!(!initial_stack.is_null(), "Arguments are not initialized");
+    unsafe { *initial_stack }
+}
+
+fn argv() -> *const *const c_char {
+    let initial_stack = INITIAL_STACK.load(Ordering::Relaxed);
+    unsafe { initial_stack.add(1) as *const *const c_char }
+}
+
+/// Returns the arguments of the process. The first argument is the program name.
+pub fn args() -> impl Iterator<Item = &'static str> {
+    strings(argv())
+}
+
+/// Returns the environment variables as key value pairs.
+pub fn env() -> impl Iterator<Item = (&'static str, &'static str)> {
+    // The environment starts after the null pointer terminating argv
+    let envp = unsafe { argv().add(argc() + 1) };
+    strings(envp).map(|var| var.split_once('=').unwrap_or((var, "")))
+}
+
+/// Returns the value of the given environment variable.
+pub fn var(key: &str) -> Option<&'static str> {
+    env().find(|(k, _)| *k == key).map(|(_, value)| value)
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+use common::syscalls::{sys_exit, sys_read, sys_write, STDIN, STDOUT};
+
+extern crate userspace;
+
+const BUFFER_SIZE: usize = 256;
+
+/// Copies the input to the output until the end of the input is reached.
+#[unsafe(no_mangle)]
+fn main() {
+    let mut buffer = [0; BUFFER_SIZE];
+    loop {
+        let count = match sys_read(STDIN, &mut buffer[0], BUFFER_SIZE) {
+            Ok(0) => return,
+            Ok(count) => count,
+            Err(_) => {
+                sys_exit(1);
+                return;
+            }
+        };
+        let mut data = &buffer[..count];
+        while !data.is_empty() {
+            match sys_write(STDOUT, &data[0], data.len()) {
+                Ok(written) => data = &data[written..],
+                Err(_) => {
+                    sys_exit(1);
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+use alloc::format;
+use common::syscalls::{
+    sys_channel_create, sys_channel_receive, sys_channel_send, sys_close, sys_pipe, sys_read,
+    sys_wait, sys_write,
+};
+use userspace::{args, println, util::execute};
+
+extern crate alloc;
+extern crate userspace;
+
+const BUFFER_SIZE: usize = 64;
+
+/// The client starts a service with the channel descriptor as argument.
+/// It sends a request together with the write end of a pipe on which the service answers.
+#[unsafe(no_mangle)]
+fn main() {
+    match args::args().nth(1) {
+        None => client(),
+        Some(descriptor) => service(descriptor.parse().expect("Descriptor must be a number")),
+    }
+}
+
+fn client() {
+    let channel = sys_channel_create().expect("Channel must be available");
+
+    // The service inherits the descriptor
+    let channel_argument = format!("{channel}");
+    let pid = execute(&["channel", &channel_argument], &[]).expect("Service must start");
+
+    let pipe = sys_pipe().expect("Pipe must be available");
+    let request = b"Hello service";
+    sys_channel_send(channel, &request[0], request.len(), pipe.write).expect("Send must succeed");
+    // The service holds the only write end now, so reading ends once it answered
+    sys_close(pipe.write).expect("Descriptor must be open");
+
+    let mut reply = [0; BUFFER_SIZE];
+    let mut length = 0;
+    loop {
+        let count = sys_read(pipe.read, &mut reply[length], BUFFER_SIZE - length)
+            .expect("Reply must be readable");
+        if count == 0 {
+            break;
+        }
+        length += count;
+    }
+    println!(
+        "Reply: {}",
+        core::str::from_utf8(&reply[..length]).expect("Reply must be UTF-8")
+    );
+
+    sys_wait(pid, false).expect("Service must exit");
+    sys_close(pipe.read).expect("Descriptor must be open");
+    sys_close(channel).expect("Descriptor must be open");
+}
+
+fn service(channel: usize) {
+    let mut request = [0; BUFFER_SIZE];
+    let message =
+        sys_channel_receive(channel, &mut request[0], BUFFER_SIZE).expect("Request must arrive");
+    let text = core::str::from_utf8(&request[..message.length]).expect("Request must be UTF-8");
+    let Some(reply_descriptor) = message.descriptor else {
+        println!("Service received '{text}' without a descriptor");
+        return;
+    };
+    println!("Service received '{text}' with a descriptor");
+
+    let reply = b"Hi client";
+    let mut written = 0;
+    while written < reply.len() {
+        written += sys_write(reply_descriptor, &reply[written], reply.len() - written)
+            .expect("Reply must be writable");
+    }
+    sys_close(reply_descriptor).expect("Descriptor must be open");
+}
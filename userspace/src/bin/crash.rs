@@ -0,0 +1,15 @@
+#![no_std]
+#![no_main]
+
+use userspace::println;
+
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    println!("Hello from Crash! Writing to an unmapped address");
+    unsafe {
+        core::ptr::with_exposed_provenance_mut::<u8>(0xdeadbeef).write_volatile(42);
+    }
+    println!("This should never be printed");
+}
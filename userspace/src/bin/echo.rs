@@ -0,0 +1,17 @@
+#![no_std]
+#![no_main]
+
+use userspace::{args, print, println};
+
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    for (index, arg) in args::args().skip(1).enumerate() {
+        if index > 0 {
+            print!(" ");
+        }
+        print!("{}", arg);
+    }
+    println!();
+}
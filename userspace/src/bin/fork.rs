@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use common::syscalls::{sys_fork, sys_wait};
+use userspace::println;
+
+extern crate userspace;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[unsafe(no_mangle)]
+fn main() {
+    let pid = sys_fork().expect("Fork must succeed");
+    if pid == 0 {
+        COUNTER.store(42, Ordering::Relaxed);
+        println!("Child: counter={}", COUNTER.load(Ordering::Relaxed));
+        return;
+    }
+    let exit = sys_wait(pid, false).unwrap();
+    assert!(exit.status.is_success());
+    println!("Parent: counter={}", COUNTER.load(Ordering::Relaxed));
+}
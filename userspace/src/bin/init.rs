@@ -1,8 +1,8 @@
 #![no_std]
 #![no_main]
 
-use common::syscalls::{sys_execute, sys_wait};
-use userspace::println;
+use common::syscalls::sys_wait;
+use userspace::{println, util::execute};
 
 extern crate userspace;
 
@@ -10,8 +10,13 @@ extern crate userspace;
 fn main() {
     println!("init process started");
     println!("starting shell");
-    let shell_name = "yash";
-    let shell_pid = sys_execute(&shell_name.as_bytes()[0], shell_name.len()).unwrap();
-    sys_wait(shell_pid as u64).unwrap();
-    println!("Initial shell has exited...");
+    let shell_pid = execute(&["yash"], &["SHELL=yash"]).unwrap();
+    // Orphans are adopted by init, therefore their exit status is collected here as well
+    loop {
+        let exit = sys_wait(0, false).unwrap();
+        if exit.pid == shell_pid {
+            println!("Initial shell has {}...", exit.status);
+            return;
+        }
+    }
 }
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+use alloc::vec::Vec;
+use common::{
+    signal::Signal,
+    syscalls::{sys_exit, sys_kill},
+};
+use userspace::{args, println};
+
+extern crate alloc;
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    let args: Vec<&str> = args::args().collect();
+    let (signal, pids) = match args.get(1) {
+        Some(arg) if arg.starts_with('-') => (parse_signal(&arg[1..]), &args[2..]),
+        _ => (Some(Signal::SIGTERM), &args[1..]),
+    };
+    let Some(signal) = signal else {
+        usage();
+    };
+    if pids.is_empty() {
+        usage();
+    }
+
+    let mut failed = false;
+    for pid in pids {
+        let Ok(pid) = pid.parse() else {
+            println!("Invalid pid {pid}");
+            failed = true;
+            continue;
+        };
+        if let Err(err) = sys_kill(pid, signal) {
+            println!("Cannot send {signal} to {pid}: {err}");
+            failed = true;
+        }
+    }
+    if failed {
+        sys_exit(1);
+    }
+}
+
+/// Accepts the number or the name of the signal with or without the SIG prefix.
+fn parse_signal(signal: &str) -> Option<Signal> {
+    if let Ok(number) = signal.parse() {
+        return Signal::new(number);
+    }
+    let name = signal.strip_prefix("SIG").unwrap_or(signal);
+    (1..Signal::COUNT)
+        .filter_map(Signal::new)
+        .find(|candidate| candidate.name().and_then(|n| n.strip_prefix("SIG")) == Some(name))
+}
+
+fn usage() -> ! {
+    println!("Usage: kill [-<signal>] <pid>...");
+    sys_exit(1);
+    unreachable!()
+}
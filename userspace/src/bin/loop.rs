@@ -1,7 +1,8 @@
 #![no_std]
 #![no_main]
 
-use userspace::{println, util::wait};
+use common::syscalls::sys_sleep;
+use userspace::println;
 
 extern crate userspace;
 
@@ -10,6 +11,6 @@ fn main() {
     println!("Hello from Loop");
     for i in 0..10 {
         println!("Looping... {}", i);
-        wait(100000000);
+        sys_sleep(500);
     }
 }
@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+use alloc::vec;
+use common::syscalls::{sys_mmap_pages, sys_mprotect, sys_munmap, MemoryProtection};
+use core::hint::black_box;
+use userspace::println;
+
+extern crate alloc;
+extern crate userspace;
+
+const PAGE_SIZE: usize = 4096;
+
+#[unsafe(no_mangle)]
+fn main() {
+    let ptr = sys_mmap_pages(4).expect("Pages must be available");
+    // SAFETY: The pages were just mapped
+    let pages = unsafe { core::slice::from_raw_parts_mut(ptr, 4 * PAGE_SIZE) };
+    pages.fill(42);
+
+    sys_munmap(ptr as usize + PAGE_SIZE, 2).expect("Pages must be mapped");
+    let reused = sys_mmap_pages(2).expect("Pages must be available");
+    println!(
+        "Freed range reused: {}",
+        reused == ptr.wrapping_add(PAGE_SIZE)
+    );
+    // SAFETY: The pages were just mapped
+    println!("New pages are zeroed: {}", unsafe { *reused } == 0);
+
+    // Much more than the memory of the machine, which only works if freed memory is returned
+    for _ in 0..64 {
+        black_box(vec![1u8; 4 * 1024 * 1024]);
+    }
+    println!("Allocated 256MiB in total");
+
+    sys_mprotect(ptr as usize, 1, MemoryProtection::READ_ONLY).expect("Page must be mapped");
+    // SAFETY: The page is still readable
+    println!("Read-only page contains {}", unsafe { ptr.read_volatile() });
+    println!("Writing to read-only page");
+    // SAFETY: The write crashes the process on purpose
+    unsafe { ptr.write_volatile(0) };
+    println!("This should never be printed");
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+use alloc::{format, string::String, vec::Vec};
+use common::syscalls::{sys_exit, sys_set_foreground, sys_setpriority, sys_wait, ExitStatus};
+use userspace::{args, println, util::execute};
+
+extern crate alloc;
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    let args: Vec<&str> = args::args().collect();
+    let Some(nice) = args.get(1).and_then(|arg| arg.parse().ok()) else {
+        usage();
+    };
+    if args.len() < 3 {
+        usage();
+    }
+
+    // The program inherits our priority
+    if let Err(err) = sys_setpriority(0, nice) {
+        println!("Cannot set nice value {nice}: {err}");
+        sys_exit(1);
+    }
+
+    let env: Vec<String> = args::env()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    let env: Vec<&str> = env.iter().map(String::as_str).collect();
+
+    let pid = match execute(&args[2..], &env) {
+        Ok(pid) => pid,
+        Err(err) => {
+            println!("Error executing program: {err}");
+            sys_exit(1);
+            return;
+        }
+    };
+    // The job might already be finished
+    let _ = sys_set_foreground(pid);
+
+    match sys_wait(pid, false).map(|exit| exit.status) {
+        Ok(ExitStatus::Exited(status)) => sys_exit(status),
+        _ => sys_exit(1),
+    }
+}
+
+fn usage() -> ! {
+    println!("Usage: nice <value> <program> [arguments]");
+    sys_exit(1);
+    unreachable!()
+}
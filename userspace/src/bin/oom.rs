@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+use common::syscalls::{
+    sys_fork, sys_mmap_pages, sys_munmap, sys_setrlimit, sys_wait, Resource, UNLIMITED,
+};
+use userspace::println;
+
+extern crate userspace;
+
+const PAGE_SIZE: usize = 4096;
+// 4 GiB which is much more than the memory of the machine
+const TOO_MANY_PAGES: usize = 1 << 20;
+
+/// A child reserves the available memory twice and touches all of it.
+/// The kernel kills it once the memory runs out instead of crashing.
+#[unsafe(no_mangle)]
+fn main() {
+    let pid = sys_fork().expect("Fork must succeed");
+    if pid == 0 {
+        exhaust_memory();
+        return;
+    }
+    let exit = sys_wait(pid, false).expect("Child must exit");
+    println!("Memory hog {}", exit.status);
+
+    let ptr = sys_mmap_pages(1).expect("Memory must be available again");
+    // SAFETY: The page was just mapped
+    unsafe { ptr.write_volatile(42) };
+    println!("Memory is available again");
+}
+
+fn exhaust_memory() {
+    sys_setrlimit(Resource::MAPPED_PAGES, UNLIMITED, UNLIMITED).expect("Limit must be valid");
+    println!(
+        "Reserving 4GiB: {}",
+        sys_mmap_pages(TOO_MANY_PAGES).unwrap_err()
+    );
+
+    let available = largest_reservation();
+    // Reservations are only backed once they are accessed, so both succeed
+    let first = sys_mmap_pages(available).expect("Memory must be available");
+    let second = sys_mmap_pages(available).expect("Memory must be available");
+    for ptr in [first, second] {
+        for page in 0..available {
+            // SAFETY: The pages are mapped. The process is killed once the memory runs out.
+            unsafe { ptr.add(page * PAGE_SIZE).write_volatile(1) };
+        }
+    }
+    println!("This should never be printed");
+}
+
+/// Number of pages the kernel is willing to reserve at once.
+fn largest_reservation() -> usize {
+    let (mut possible, mut impossible) = (0, TOO_MANY_PAGES);
+    while impossible - possible > 1 {
+        let pages = possible + (impossible - possible) / 2;
+        match sys_mmap_pages(pages) {
+            Ok(ptr) => {
+                sys_munmap(ptr as usize, pages).expect("Pages must be mapped");
+                possible = pages;
+            }
+            Err(_) => impossible = pages,
+        }
+    }
+    possible
+}
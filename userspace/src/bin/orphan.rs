@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+use common::syscalls::{
+    sys_close, sys_exit, sys_fork, sys_getpid, sys_getppid, sys_pipe, sys_read, sys_sleep, sys_wait,
+};
+use userspace::println;
+
+extern crate userspace;
+
+/// The middle process exits right after starting the grandchild,
+/// which is then adopted by init.
+#[unsafe(no_mangle)]
+fn main() {
+    // The grandchild holds the only write end once the middle process is gone
+    let pipe = sys_pipe().expect("Pipe must be available");
+
+    let middle = sys_fork().expect("Fork must succeed");
+    if middle == 0 {
+        let middle = sys_getpid();
+        if sys_fork().expect("Fork must succeed") == 0 {
+            grandchild(middle);
+            return;
+        }
+        sys_exit(0);
+        return;
+    }
+    sys_close(pipe.write).expect("Descriptor must be open");
+    sys_wait(middle, false).expect("Middle process must exit");
+
+    // Reading ends once the grandchild exited
+    let mut buffer = [0; 1];
+    while sys_read(pipe.read, &mut buffer[0], 1) != Ok(0) {}
+    println!(
+        "Grandchild is no child of ours: {}",
+        sys_wait(0, true).is_err()
+    );
+}
+
+fn grandchild(middle: u64) {
+    while sys_getppid() == middle {
+        sys_sleep(10);
+    }
+    println!("Grandchild adopted by pid {}", sys_getppid());
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+use alloc::{vec, vec::Vec};
+use common::syscalls::{sys_exit, sys_list_processes, ProcessInfo, ProcessState, SysError};
+use userspace::println;
+
+extern crate alloc;
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    let processes = match list_processes() {
+        Ok(processes) => processes,
+        Err(err) => {
+            println!("Cannot list processes: {err}");
+            sys_exit(1);
+            return;
+        }
+    };
+
+    println!("  PID  PPID STATE     USER(ms) KERNEL(ms)  PAGES NAME");
+    for process in processes {
+        let state = match process.state {
+            ProcessState::Running => "running",
+            ProcessState::Runnable => "runnable",
+            ProcessState::Waiting => "waiting",
+            ProcessState::Zombie => "zombie",
+        };
+        println!(
+            "{:>5} {:>5} {:<8} {:>9} {:>10} {:>6} {}",
+            process.pid,
+            process.parent_pid,
+            state,
+            process.user_time,
+            process.kernel_time,
+            process.resident_pages,
+            process.name()
+        );
+    }
+}
+
+fn list_processes() -> Result<Vec<ProcessInfo>, SysError> {
+    let mut processes = vec![ProcessInfo::default(); 16];
+    loop {
+        let count = processes.len();
+        let total = sys_list_processes(&mut processes[0], count)?;
+        // Processes might have been started in the meantime
+        if total <= count {
+            processes.truncate(total);
+            return Ok(processes);
+        }
+        processes.resize(total, ProcessInfo::default());
+    }
+}
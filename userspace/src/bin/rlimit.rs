@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+use common::syscalls::{
+    sys_fork, sys_getrlimit, sys_mmap_pages, sys_pipe, sys_setrlimit, sys_wait, Resource,
+    ResourceLimit,
+};
+use userspace::println;
+
+extern crate userspace;
+
+/// Lowers every limit once and shows how the kernel refuses to exceed it.
+#[unsafe(no_mangle)]
+fn main() {
+    with_limit(Resource::DESCRIPTORS, 3, || {
+        println!("pipe: {}", sys_pipe().unwrap_err());
+    });
+    with_limit(Resource::MAPPED_PAGES, 0, || {
+        println!("mmap: {}", sys_mmap_pages(1).unwrap_err());
+    });
+    with_limit(Resource::CHILDREN, 0, || {
+        println!("fork: {}", sys_fork().unwrap_err());
+    });
+
+    let pid = sys_fork().expect("Fork must succeed");
+    if pid == 0 {
+        sys_setrlimit(Resource::CPU_TIME, 100, 100).expect("Limit must be valid");
+        #[allow(clippy::empty_loop)]
+        loop {}
+    }
+    let exit = sys_wait(pid, false).expect("Child must exit");
+    println!("busy child {}", exit.status);
+
+    let limit = get_limit(Resource::CHILDREN);
+    sys_setrlimit(Resource::CHILDREN, limit.soft, limit.soft).expect("Lowering must succeed");
+    println!(
+        "raise: {}",
+        sys_setrlimit(Resource::CHILDREN, limit.soft, limit.hard).unwrap_err()
+    );
+}
+
+/// Only lowers the soft limit such that it can be restored afterwards.
+fn with_limit(resource: Resource, limit: usize, f: impl FnOnce()) {
+    let previous = get_limit(resource);
+    sys_setrlimit(resource, limit, previous.hard).expect("Limit must be valid");
+    f();
+    sys_setrlimit(resource, previous.soft, previous.hard).expect("Previous limit must be valid");
+}
+
+fn get_limit(resource: Resource) -> ResourceLimit {
+    let mut limit = ResourceLimit { soft: 0, hard: 0 };
+    sys_getrlimit(resource, &mut limit).expect("Resource must exist");
+    limit
+}
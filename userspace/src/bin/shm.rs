@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+use alloc::format;
+use common::syscalls::{sys_close, sys_munmap, sys_shm_create, sys_shm_map, sys_wait};
+use userspace::{args, println, util::execute};
+
+extern crate alloc;
+extern crate userspace;
+
+const PAGE_SIZE: usize = 4096;
+const NUMBER_OF_PAGES: usize = 16;
+const SIZE: usize = NUMBER_OF_PAGES * PAGE_SIZE;
+
+fn map(descriptor: usize) -> &'static mut [u8] {
+    let ptr = sys_shm_map(descriptor, NUMBER_OF_PAGES).expect("Shared memory must be mappable");
+    // SAFETY: The pages were just mapped and are only accessed by one process at a time
+    unsafe { core::slice::from_raw_parts_mut(ptr, SIZE) }
+}
+
+fn pattern(index: usize) -> u8 {
+    (index % 251) as u8
+}
+
+/// The producer passes a buffer to a consumer which it starts with the descriptor as argument.
+#[unsafe(no_mangle)]
+fn main() {
+    match args::args().nth(1) {
+        None => produce(),
+        Some(descriptor) => consume(descriptor.parse().expect("Descriptor must be a number")),
+    }
+}
+
+fn produce() {
+    let descriptor = sys_shm_create(NUMBER_OF_PAGES).expect("Shared memory must be available");
+    let buffer = map(descriptor);
+    for (index, byte) in buffer.iter_mut().enumerate() {
+        *byte = pattern(index);
+    }
+
+    // The consumer inherits the descriptor
+    let descriptor_argument = format!("{descriptor}");
+    let pid = execute(&["shm", &descriptor_argument], &[]).expect("Consumer must start");
+    sys_wait(pid, false).expect("Consumer must exit");
+
+    println!("Producer received answer: {}", buffer[0]);
+    sys_munmap(buffer.as_ptr() as usize, NUMBER_OF_PAGES).expect("Pages must be mapped");
+    sys_close(descriptor).expect("Descriptor must be open");
+}
+
+fn consume(descriptor: usize) {
+    let buffer = map(descriptor);
+    let intact = buffer
+        .iter()
+        .enumerate()
+        .all(|(index, byte)| *byte == pattern(index));
+    println!("Consumer received {} bytes: {}", buffer.len(), intact);
+    buffer[0] = 42;
+}
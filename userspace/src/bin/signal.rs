@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use common::syscalls::{sys_exit, sys_fork, sys_kill, sys_wait, sys_yield, ExitStatus};
+use userspace::{
+    println,
+    signal::{self, Signal, SignalSet},
+};
+
+extern crate userspace;
+
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handler(signal: Signal) {
+    RECEIVED.store(signal.number(), Ordering::Relaxed);
+}
+
+#[unsafe(no_mangle)]
+fn main() {
+    // The handler is inherited by the child
+    signal::set_handler(Signal::SIGUSR1, handler).unwrap();
+
+    let pid = sys_fork().expect("Fork must succeed");
+    if pid == 0 {
+        while RECEIVED.load(Ordering::Relaxed) == 0 {
+            sys_yield();
+        }
+        println!(
+            "Child received {:?}",
+            Signal::new(RECEIVED.load(Ordering::Relaxed)).unwrap()
+        );
+        sys_exit(0);
+    }
+    sys_kill(pid, Signal::SIGUSR1).unwrap();
+    assert!(sys_wait(pid, false).unwrap().status.is_success());
+
+    // Ignored and blocked signals are inherited as well
+    signal::ignore(Signal::SIGTERM).unwrap();
+    let previously_blocked = signal::block(SignalSet::all());
+    let pid = sys_fork().expect("Fork must succeed");
+    if pid == 0 {
+        #[allow(clippy::empty_loop)]
+        loop {}
+    }
+    signal::reset(Signal::SIGTERM).unwrap();
+    signal::unblock(SignalSet::all().difference(previously_blocked));
+
+    sys_kill(pid, Signal::SIGTERM).unwrap();
+    sys_kill(pid, Signal::SIGUSR2).unwrap();
+    sys_kill(pid, Signal::SIGKILL).unwrap();
+    let exit = sys_wait(pid, false).unwrap();
+    assert_eq!(exit.status, ExitStatus::Signaled(Signal::SIGKILL));
+    println!("Child {}", exit.status);
+}
@@ -0,0 +1,17 @@
+#![no_std]
+#![no_main]
+
+use common::syscalls::{sys_exit, sys_sleep};
+use userspace::{args, println};
+
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    let Some(milliseconds) = args::args().nth(1).and_then(|arg| arg.parse().ok()) else {
+        println!("Usage: sleep <milliseconds>");
+        sys_exit(1);
+        return;
+    };
+    sys_sleep(milliseconds);
+}
@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+
+use userspace::println;
+
+extern crate userspace;
+
+// Every call puts 1KB on the stack
+fn use_stack(depth: usize) -> usize {
+    let buffer = black_box([depth as u8; 1024]);
+    if depth == 0 {
+        return 0;
+    }
+    use_stack(depth - 1) + black_box(buffer)[1023] as usize
+}
+
+fn recurse_forever(depth: usize) -> usize {
+    let buffer = black_box([depth as u8; 1024]);
+    if black_box(false) {
+        return 0;
+    }
+    recurse_forever(depth + 1) + black_box(buffer)[1023] as usize
+}
+
+#[unsafe(no_mangle)]
+fn main() {
+    println!("Using 64KB of stack: {}", use_stack(64));
+    println!("Recursing without end");
+    recurse_forever(0);
+    println!("This should never be printed");
+}
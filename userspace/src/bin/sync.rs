@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use common::syscalls::sys_yield;
+use userspace::{
+    println,
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+extern crate alloc;
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    // Yielding inside the critical section lets the other threads block on the lock
+    let counter = Arc::new(Mutex::new(0));
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                for _ in 0..100 {
+                    let mut counter = counter.lock();
+                    let value = *counter;
+                    sys_yield();
+                    *counter = value + 1;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join();
+    }
+    println!("Counter is {}", *counter.lock());
+
+    let queue = Arc::new((Mutex::new(VecDeque::new()), Condvar::new()));
+    let producer_queue = queue.clone();
+    let producer = thread::spawn(move || {
+        let (items, condvar) = &*producer_queue;
+        for i in 1..=3 {
+            items.lock().push_back(i);
+            condvar.notify_one();
+            sys_yield();
+        }
+    });
+
+    let (items, condvar) = &*queue;
+    for _ in 1..=3 {
+        let mut items = condvar.wait_while(items.lock(), |items| items.is_empty());
+        println!("Consumed {}", items.pop_front().unwrap());
+    }
+    producer.join();
+}
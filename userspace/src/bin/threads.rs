@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+#![feature(thread_local)]
+
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{sync::Arc, vec::Vec};
+use common::syscalls::sys_yield;
+use userspace::{println, thread};
+
+extern crate alloc;
+extern crate userspace;
+
+#[thread_local]
+static COUNTER: Cell<usize> = Cell::new(0);
+
+#[unsafe(no_mangle)]
+fn main() {
+    let shared = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (1..=3)
+        .map(|i| {
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for _ in 0..i * 100 {
+                    COUNTER.set(COUNTER.get() + 1);
+                    shared.fetch_add(1, Ordering::Relaxed);
+                    sys_yield();
+                }
+                COUNTER.get()
+            })
+        })
+        .collect();
+
+    for (i, handle) in handles.into_iter().enumerate() {
+        println!("Thread {} counted {}", i + 1, handle.join());
+    }
+    println!("Main thread counted {}", COUNTER.get());
+    println!("All threads counted {}", shared.load(Ordering::Relaxed));
+}
@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+use common::syscalls::{sys_exit, sys_fork, sys_wait, ExitStatus, SysError};
+use userspace::println;
+
+extern crate userspace;
+
+#[unsafe(no_mangle)]
+fn main() {
+    let pid = sys_fork().expect("Fork must succeed");
+    if pid == 0 {
+        sys_exit(3);
+    }
+
+    let exit = sys_wait(0, false).unwrap();
+    assert_eq!(exit.pid, pid);
+    assert_eq!(exit.status, ExitStatus::Exited(3));
+    println!("Child {}", exit.status);
+
+    if let Err(SysError::NoChildren) = sys_wait(0, true) {
+        println!("No more children");
+    }
+}
@@ -1,9 +1,20 @@
 #![no_std]
 #![no_main]
 
-use alloc::string::{String, ToString};
-use common::syscalls::{sys_execute, sys_exit, sys_print_programs, sys_wait};
-use userspace::{print, println, util::read_line};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use common::syscalls::{
+    sys_close, sys_dup, sys_dup2, sys_exit, sys_pipe, sys_print_programs, sys_set_foreground,
+    sys_times, sys_wait, ProcessTimes, SysError, STDIN, STDOUT,
+};
+use userspace::{
+    args, print, println,
+    signal::{self, Signal},
+    util::{execute, read_line},
+};
 
 extern crate alloc;
 extern crate userspace;
@@ -13,15 +24,36 @@ fn main() {
     println!();
     println!("### YaSH - Yet another Shell ###");
     println!("Type 'help' for a list of available commands.");
+    // Ctrl+C is meant for the foreground job
+    signal::ignore(Signal::SIGINT).unwrap();
+    let mut env: Vec<String> = args::env()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
     loop {
+        reap_background_jobs();
         print!("$ ");
         let input = read_line();
         // Parse input and execute
-        parse_command_and_execute(input);
+        parse_command_and_execute(input, &mut env);
     }
 }
 
-fn parse_command_and_execute(mut command: String) {
+fn reap_background_jobs() {
+    while let Ok(exit) = sys_wait(0, true) {
+        println!("[{}] {}", exit.pid, exit.status);
+    }
+}
+
+fn export(variable: &str, env: &mut Vec<String>) {
+    let Some((key, _)) = variable.split_once('=') else {
+        println!("Usage: export KEY=VALUE");
+        return;
+    };
+    env.retain(|var| var.split_once('=').is_none_or(|(k, _)| k != key));
+    env.push(variable.to_string());
+}
+
+fn parse_command_and_execute(mut command: String, env: &mut Vec<String>) {
     command = command.trim().to_string();
     match command.as_str() {
         "" => {}
@@ -32,7 +64,10 @@ fn parse_command_and_execute(mut command: String) {
         "help" => {
             println!("Available commands:");
             println!("exit - Exit the shell");
+            println!("export KEY=VALUE - Set an environment variable");
             println!("help - Print this help message");
+            println!("a | b - Run a and b with the output of a as input of b");
+            println!("time a - Run a and print the elapsed and the consumed cpu time");
             println!("\nFollowing programs exist and can be called:");
             sys_print_programs();
         }
@@ -42,23 +77,134 @@ fn parse_command_and_execute(mut command: String) {
             if command.ends_with('&') {
                 background = true;
                 command.pop();
-                command = command.trim().to_string();
             }
 
-            let reference = unsafe { &*command.as_ptr() };
-            let len = command.len();
+            let timed = command.starts_with("time ");
+            if timed {
+                command.drain(.."time".len());
+            }
+
+            let commands: Vec<Vec<&str>> = command
+                .split('|')
+                .map(|command| command.split_whitespace().collect())
+                .collect();
 
-            let execute_result = sys_execute(reference, len);
-            match execute_result {
-                Ok(pid) => {
-                    if !background {
-                        let _ = sys_wait(pid);
+            match commands[0].first() {
+                None if commands.len() == 1 => return,
+                Some(&"export") if commands.len() == 1 => {
+                    for variable in &commands[0][1..] {
+                        export(variable, env);
                     }
+                    return;
                 }
-                Err(err) => {
-                    println!("Error executing program: {:?}", err);
+                _ => {}
+            }
+
+            if commands.iter().any(Vec::is_empty) {
+                println!("Missing program in pipeline");
+                return;
+            }
+
+            let env: Vec<&str> = env.iter().map(String::as_str).collect();
+
+            let before = timed.then(current_times);
+
+            let mut pids = Vec::new();
+            if let Err(err) = start_pipeline(&commands, &env, &mut pids) {
+                println!("Error executing program: {}", err);
+                // The already started programs get an error or the end of their input
+                for pid in pids {
+                    let _ = sys_wait(pid, false);
                 }
+                return;
+            }
+
+            let last = *pids.last().expect("Pipeline must not be empty");
+            if background {
+                println!("[{}]", last);
+                return;
             }
+            // The job might already be finished
+            let _ = sys_set_foreground(last);
+            for pid in pids {
+                if let Ok(exit) = sys_wait(pid, false) {
+                    if pid == last && !exit.status.is_success() {
+                        println!("{}", exit.status);
+                    }
+                }
+            }
+
+            if let Some((started_at, times_before)) = before {
+                let (finished_at, times_after) = current_times();
+                // The cpu time of the pipeline is added to ours once we collected its exit status
+                let consumed = times_after.children - times_before.children;
+                println!(
+                    "real {}ms user {}ms sys {}ms",
+                    finished_at - started_at,
+                    consumed.user,
+                    consumed.kernel
+                );
+            }
+        }
+    }
+}
+
+/// Returns the milliseconds since boot and the consumed cpu time.
+fn current_times() -> (u64, ProcessTimes) {
+    let mut times = ProcessTimes::default();
+    let now = sys_times(&mut times).expect("Times must be writable");
+    (now, times)
+}
+
+/// Starts all programs of the pipeline. The output of every program is the input of the next one.
+fn start_pipeline(
+    commands: &[Vec<&str>],
+    env: &[&str],
+    pids: &mut Vec<u64>,
+) -> Result<(), SysError> {
+    if let [args] = commands {
+        pids.push(execute(args, env)?);
+        return Ok(());
+    }
+
+    // The programs inherit the input and output of the shell. The shell keeps copies which
+    // the programs do not inherit, otherwise they would keep the pipes of the shell open.
+    let saved_stdin = sys_dup(STDIN, true)?;
+    let saved_stdout = match sys_dup(STDOUT, true) {
+        Ok(descriptor) => descriptor,
+        Err(err) => {
+            sys_close(saved_stdin)?;
+            return Err(err);
+        }
+    };
+    let result = start_connected(commands, env, pids, saved_stdout);
+    sys_dup2(saved_stdin, STDIN)?;
+    sys_dup2(saved_stdout, STDOUT)?;
+    sys_close(saved_stdin)?;
+    sys_close(saved_stdout)?;
+    result
+}
+
+fn start_connected(
+    commands: &[Vec<&str>],
+    env: &[&str],
+    pids: &mut Vec<u64>,
+    saved_stdout: usize,
+) -> Result<(), SysError> {
+    for (index, args) in commands.iter().enumerate() {
+        if index + 1 == commands.len() {
+            sys_dup2(saved_stdout, STDOUT)?;
+            pids.push(execute(args, env)?);
+            break;
         }
+        let pipe = sys_pipe()?;
+        sys_dup2(pipe.write, STDOUT)?;
+        sys_close(pipe.write)?;
+        let pid = execute(args, env);
+        // The next program reads what this one writes
+        sys_dup2(pipe.read, STDIN)?;
+        sys_close(pipe.read)?;
+        pids.push(pid?);
     }
+    Ok(())
 }
@@ -10,7 +10,9 @@ use core::{
     ptr::{null_mut, NonNull},
 };
 
-use common::{mutex::Mutex, syscalls::sys_mmap_pages};
+use common::syscalls::{sys_mmap_pages, sys_munmap};
+
+use crate::sync::Mutex;
 
 const PAGE_SIZE: usize = 4096;
 
@@ -68,7 +70,7 @@ impl Pages for [Page] {
 
 pub trait PageAllocator {
     fn alloc(number_of_pages_requested: usize) -> Option<Range<NonNull<Page>>>;
-    fn dealloc(page: NonNull<Page>);
+    fn dealloc(page: NonNull<Page>, number_of_pages: usize);
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -224,9 +226,10 @@ impl<Allocator: PageAllocator> Heap<Allocator> {
     fn dealloc(&mut self, ptr: *mut u8, layout: core::alloc::Layout) {
         assert!(!ptr.is_null());
         if self.is_page_allocator_allocation(&layout) {
-            // Deallocate directly to the page allocator
+            // Large blocks are given back to the kernel directly
+            let pages = minimum_amount_of_pages(layout.size());
             unsafe {
-                Allocator::dealloc(NonNull::new_unchecked(ptr).cast());
+                Allocator::dealloc(NonNull::new_unchecked(ptr).cast(), pages);
             }
             return;
         }
@@ -308,19 +311,16 @@ struct KernelSyscallAllocator;
 
 impl PageAllocator for KernelSyscallAllocator {
     fn alloc(number_of_pages_requested: usize) -> Option<Range<NonNull<Page>>> {
-        let ptr = sys_mmap_pages(number_of_pages_requested) as *mut Page;
-        if ptr.is_null() {
-            return None;
-        }
-        // SAFETY: We allready checked for a null ptr
+        let ptr = sys_mmap_pages(number_of_pages_requested).ok()? as *mut Page;
+        // SAFETY: The kernel never maps the null page
         unsafe {
             let end = ptr.add(number_of_pages_requested);
             Some(NonNull::new_unchecked(ptr)..NonNull::new_unchecked(end))
         }
     }
 
-    fn dealloc(page: NonNull<Page>) {
-        todo!()
+    fn dealloc(page: NonNull<Page>, number_of_pages: usize) {
+        sys_munmap(page.as_ptr() as usize, number_of_pages).expect("Pages must be mapped");
     }
 }
 
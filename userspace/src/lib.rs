@@ -1,10 +1,15 @@
 #![no_std]
+#![feature(thread_local)]
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
 mod _start;
+pub mod args;
 mod heap;
 pub mod net;
 mod panic;
 pub mod print;
+pub mod signal;
+pub mod sync;
+pub mod thread;
 pub mod util;
@@ -1,25 +1,40 @@
-use common::{
-    net::UDPDescriptor,
-    syscalls::{
-        sys_open_udp_socket, sys_read_udp_socket, sys_write_back_udp_socket, SysSocketError,
-    },
-};
+use common::syscalls::{sys_close, sys_open_udp_socket, sys_read, sys_sleep, sys_write, SysError};
 
-pub struct UdpSocket(UDPDescriptor);
+// The network card does not raise interrupts, therefore we have to poll for packets
+const POLL_INTERVAL_MS: u64 = 10;
+
+pub struct UdpSocket(usize);
 
 impl UdpSocket {
-    pub fn try_open(port: u16) -> Result<Self, SysSocketError> {
+    pub fn try_open(port: u16) -> Result<Self, SysError> {
         sys_open_udp_socket(port).map(Self)
     }
 
-    pub fn receive(&mut self, buffer: &mut [u8]) -> usize {
+    pub fn receive(&self, buffer: &mut [u8]) -> usize {
         let len = buffer.len();
-        sys_read_udp_socket(self.0, &mut buffer[0], len)
+        sys_read(self.0, &mut buffer[0], len)
             .expect("This must succeed since it is a valid descriptor.")
     }
 
-    pub fn transmit(&mut self, buffer: &[u8]) -> usize {
+    /// Waits until at least one byte was received.
+    pub fn receive_blocking(&self, buffer: &mut [u8]) -> usize {
+        loop {
+            let count = self.receive(buffer);
+            if count > 0 {
+                return count;
+            }
+            sys_sleep(POLL_INTERVAL_MS);
+        }
+    }
+
+    pub fn transmit(&self, buffer: &[u8]) -> usize {
         let len = buffer.len();
-        sys_write_back_udp_socket(self.0, &buffer[0], len).expect("Sending must be successful.")
+        sys_write(self.0, &buffer[0], len).expect("Sending must be successful.")
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        let _ = sys_close(self.0);
     }
 }
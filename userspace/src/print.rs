@@ -1,6 +1,11 @@
-use core::fmt::{self, Write};
+use core::{
+    cell::Cell,
+    fmt::{self, Write},
+};
 
-use common::{mutex::Mutex, syscalls::sys_write_char};
+use common::syscalls::{sys_write, STDOUT};
+
+use crate::sync::Mutex;
 
 #[macro_export]
 macro_rules! print {
@@ -15,18 +20,91 @@ macro_rules! println {
 
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
-    WRITER.lock().write_fmt(args).unwrap();
+    let buffered = with_writer(|writer| {
+        writer.write_fmt(args).unwrap();
+        // Also an unterminated line like a prompt must be visible right away
+        // and must not get lost if the process exits directly
+        writer.flush();
+    });
+    if !buffered {
+        // Errors are discarded for the same reason as in flush
+        let _ = Unbuffered.write_fmt(args);
+    }
+}
+
+/// Returns false without calling f if this thread already holds the writer, which happens if
+/// a signal handler or a panic prints while the thread is printing. Locking again would
+/// wait forever and the buffer might be in the middle of a change.
+fn with_writer(f: impl FnOnce(&mut Writer)) -> bool {
+    if HOLDS_WRITER.get() {
+        return false;
+    }
+    let mut writer = WRITER.lock();
+    HOLDS_WRITER.set(true);
+    f(&mut writer);
+    HOLDS_WRITER.set(false);
+    true
+}
+
+fn write_all(mut data: &[u8]) {
+    while !data.is_empty() {
+        // Panicking would print again, therefore the output is discarded on errors
+        let Ok(written) = sys_write(STDOUT, &data[0], data.len()) else {
+            break;
+        };
+        data = &data[written..];
+    }
 }
 
-struct Writer;
+const BUFFER_SIZE: usize = 256;
+
+/// Buffers the output of one print to save syscalls. Complete lines are written right away.
+struct Writer {
+    buffer: [u8; BUFFER_SIZE],
+    length: usize,
+}
+
+impl Writer {
+    const fn new() -> Self {
+        Self {
+            buffer: [0; BUFFER_SIZE],
+            length: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        write_all(&self.buffer[..self.length]);
+        self.length = 0;
+    }
+}
 
 impl Write for Writer {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            sys_write_char(c);
+        for &byte in s.as_bytes() {
+            if self.length == BUFFER_SIZE {
+                self.flush();
+            }
+            self.buffer[self.length] = byte;
+            self.length += 1;
+            if byte == b'\n' {
+                self.flush();
+            }
         }
         Ok(())
     }
 }
 
-static WRITER: Mutex<Writer> = Mutex::new(Writer);
+/// Writes directly without buffering.
+struct Unbuffered;
+
+impl Write for Unbuffered {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_all(s.as_bytes());
+        Ok(())
+    }
+}
+
+static WRITER: Mutex<Writer> = Mutex::new(Writer::new());
+
+#[thread_local]
+static HOLDS_WRITER: Cell<bool> = Cell::new(false);
@@ -0,0 +1,45 @@
+use core::arch::global_asm;
+
+pub use common::signal::{Signal, SignalSet};
+use common::{
+    signal::{SIG_DFL, SIG_IGN},
+    syscalls::{sys_sigaction, sys_sigprocmask, SysError, Syscalls},
+};
+
+// Signal handlers return to this trampoline. The stack pointer points to the
+// signal frame at this point, therefore the stack must not be touched.
+global_asm!(
+    ".global __signal_restorer",
+    "__signal_restorer:",
+    "li a0, {sigreturn}",
+    "ecall",
+    sigreturn = const Syscalls::sys_sigreturn as usize,
+);
+
+unsafe extern "C" {
+    fn __signal_restorer();
+}
+
+pub type SignalHandler = extern "C" fn(Signal);
+
+pub fn set_handler(signal: Signal, handler: SignalHandler) -> Result<(), SysError> {
+    sys_sigaction(signal, handler as usize, __signal_restorer as usize)
+}
+
+pub fn ignore(signal: Signal) -> Result<(), SysError> {
+    sys_sigaction(signal, SIG_IGN, 0)
+}
+
+pub fn reset(signal: Signal) -> Result<(), SysError> {
+    sys_sigaction(signal, SIG_DFL, 0)
+}
+
+/// Returns the previously blocked signals
+pub fn block(signals: SignalSet) -> SignalSet {
+    sys_sigprocmask(signals, SignalSet::empty())
+}
+
+/// Returns the previously blocked signals
+pub fn unblock(signals: SignalSet) -> SignalSet {
+    sys_sigprocmask(SignalSet::empty(), signals)
+}
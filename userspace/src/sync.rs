@@ -0,0 +1,144 @@
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use common::syscalls::{sys_futex_wait, sys_futex_wake};
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+// Locked and at least one thread might wait for the lock
+const CONTENDED: u32 = 2;
+
+fn futex_wait(futex: &AtomicU32, expected: u32) {
+    // The value changed in the meantime, which is as good as a wakeup
+    let _ = sys_futex_wait(futex.as_ptr() as usize, expected);
+}
+
+fn futex_wake(futex: &AtomicU32, count: usize) {
+    sys_futex_wake(futex.as_ptr() as usize, count).expect("Futex must be valid");
+}
+
+/// A mutex which can be used by multiple threads of a process.
+/// Waiting threads sleep in the kernel until the lock is released.
+pub struct Mutex<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: The lock guarantees exclusive access to the data
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(data: T) -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            self.lock_contended();
+        }
+        MutexGuard { mutex: self }
+    }
+
+    fn lock_contended(&self) {
+        // We might not be the only waiter, therefore the unlock must always wake someone up
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            futex_wait(&self.state, CONTENDED);
+        }
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We hold the lock
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: We hold the lock
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+/// Lets threads wait until another thread notifies them about a change
+/// of the data protected by a [`Mutex`].
+pub struct Condvar {
+    // Incremented on every notification such that no notification is lost
+    // between releasing the mutex and waiting on the futex
+    sequence: AtomicU32,
+}
+
+impl Condvar {
+    pub const fn new() -> Self {
+        Self {
+            sequence: AtomicU32::new(0),
+        }
+    }
+
+    /// Releases the lock while waiting and acquires it again before returning.
+    /// Spurious wakeups are possible, therefore the condition must be checked again.
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        let mutex = guard.mutex;
+        drop(guard);
+        futex_wait(&self.sequence, sequence);
+        mutex.lock()
+    }
+
+    /// Waits as long as the condition is true.
+    pub fn wait_while<'a, T>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> MutexGuard<'a, T> {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    pub fn notify_one(&self) {
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+        futex_wake(&self.sequence, 1);
+    }
+
+    pub fn notify_all(&self) {
+        self.sequence.fetch_add(1, Ordering::Relaxed);
+        futex_wake(&self.sequence, usize::MAX);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,160 @@
+extern crate alloc;
+
+use core::{alloc::Layout, arch::asm};
+
+use alloc::{
+    alloc::{alloc_zeroed, dealloc},
+    boxed::Box,
+    sync::Arc,
+};
+use common::syscalls::{
+    sys_mmap_pages, sys_munmap, sys_thread_create, sys_thread_exit, sys_thread_join,
+};
+
+use crate::sync::Mutex;
+
+const PAGE_SIZE: usize = 4096;
+const STACK_SIZE: usize = 16 * PAGE_SIZE;
+
+unsafe extern "C" {
+    // Defined in the linker script
+    static __tls_start: u8;
+    static __tdata_end: u8;
+    static __tls_end: u8;
+    static __tls_align: u8;
+}
+
+/// The thread-local storage of a thread. The tp register points to its start,
+/// which is where the compiler expects the thread-local variables on RISC-V.
+struct ThreadLocalStorage {
+    block: *mut u8,
+}
+
+impl ThreadLocalStorage {
+    fn layout() -> Layout {
+        let start = &raw const __tls_start as usize;
+        let end = &raw const __tls_end as usize;
+        let align = &raw const __tls_align as usize;
+        Layout::from_size_align(end - start, align.max(1)).expect("TLS layout must be valid")
+    }
+
+    fn new() -> Self {
+        let layout = Self::layout();
+        if layout.size() == 0 {
+            return Self {
+                block: core::ptr::without_provenance_mut(layout.align()),
+            };
+        }
+
+        let template = &raw const __tls_start;
+        let template_size = &raw const __tdata_end as usize - template as usize;
+        // SAFETY: The layout has a non-zero size and the template is part of our binary.
+        // The remaining part (.tbss) is already zeroed.
+        unsafe {
+            let block = alloc_zeroed(layout);
+            assert!(!block.is_null(), "Cannot allocate thread-local storage");
+            core::ptr::copy_nonoverlapping(template, block, template_size);
+            Self { block }
+        }
+    }
+
+    /// # Safety
+    /// Must be called at most once per thread before any thread-local variable is accessed.
+    unsafe fn activate(&self) {
+        unsafe {
+            asm!("mv tp, {}", in(reg) self.block);
+        }
+    }
+
+    /// # Safety
+    /// The thread using the storage must have exited.
+    unsafe fn free(self) {
+        let layout = Self::layout();
+        if layout.size() != 0 {
+            unsafe { dealloc(self.block, layout) };
+        }
+    }
+}
+
+pub(crate) fn init_main_thread() {
+    let tls = ThreadLocalStorage::new();
+    // SAFETY: Called once by _start before main runs.
+    // The storage of the main thread lives until the process exits.
+    unsafe { tls.activate() };
+}
+
+struct ThreadStart {
+    main: Box<dyn FnOnce() + Send>,
+    tls: ThreadLocalStorage,
+}
+
+extern "C" fn thread_start(start: *mut ThreadStart) -> ! {
+    // SAFETY: spawn hands over the ownership of the start information
+    let start = unsafe { Box::from_raw(start) };
+    let ThreadStart { main, tls } = *start;
+    unsafe { tls.activate() };
+    main();
+    sys_thread_exit(0);
+    unreachable!("Thread must not continue after exit");
+}
+
+pub struct JoinHandle<T> {
+    tid: u64,
+    result: Arc<Mutex<Option<T>>>,
+    tls: ThreadLocalStorage,
+    stack: usize,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn tid(&self) -> u64 {
+        self.tid
+    }
+
+    /// Waits for the thread to finish and returns its result.
+    pub fn join(self) -> T {
+        sys_thread_join(self.tid).expect("Thread must be joinable");
+        // SAFETY: The thread has exited
+        unsafe { self.tls.free() };
+        sys_munmap(self.stack, STACK_SIZE / PAGE_SIZE).expect("Stack must be mapped");
+        self.result
+            .lock()
+            .take()
+            .expect("Thread must have stored its result")
+    }
+}
+
+/// Starts a new thread which shares the memory with the calling thread.
+/// If the handle is dropped without a join, the thread keeps running detached.
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let result = Arc::new(Mutex::new(None));
+    let thread_result = result.clone();
+    let tls = ThreadLocalStorage::new();
+    let start = Box::new(ThreadStart {
+        main: Box::new(move || {
+            let value = f();
+            *thread_result.lock() = Some(value);
+        }),
+        tls: ThreadLocalStorage { block: tls.block },
+    });
+
+    // The stack of a detached thread is leaked
+    let stack = sys_mmap_pages(STACK_SIZE / PAGE_SIZE).expect("Cannot allocate thread stack");
+    let stack_pointer = stack as usize + STACK_SIZE;
+
+    let tid = sys_thread_create(
+        thread_start as usize,
+        stack_pointer,
+        Box::into_raw(start) as usize,
+    );
+
+    JoinHandle {
+        tid,
+        result,
+        tls,
+        stack: stack as usize,
+    }
+}
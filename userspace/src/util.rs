@@ -1,25 +1,33 @@
 extern crate alloc;
 
-use alloc::string::String;
-use common::syscalls::sys_read_input_wait;
-use core::arch::asm;
+use alloc::{string::String, vec::Vec};
+use common::syscalls::{sys_execute, sys_read, SysError, STDIN};
 
 use crate::{print, println};
 
 const DELETE: u8 = 127;
 
-pub fn wait(cycles: usize) {
-    for _ in 0..cycles {
-        unsafe {
-            asm!("nop");
-        }
+/// Starts a new process. The first argument is the name of the program.
+pub fn execute(args: &[&str], env: &[&str]) -> Result<u64, SysError> {
+    if args.is_empty() {
+        return Err(SysError::InvalidArgument);
+    }
+    let mut data = Vec::new();
+    for string in args.iter().chain(env) {
+        data.extend_from_slice(string.as_bytes());
+        data.push(0);
     }
+    sys_execute(&data[0], data.len(), args.len())
 }
 
 pub fn read_line() -> String {
     let mut input = String::new();
     loop {
-        let result = sys_read_input_wait();
+        let mut result = 0;
+        if sys_read(STDIN, &mut result, 1) != Ok(1) {
+            // End of the input
+            break;
+        }
         match result {
             b'\r' | b'\n' => {
                 // Carriage return